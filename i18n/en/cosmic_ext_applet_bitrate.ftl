@@ -1,12 +1,201 @@
+app-name = Bitrate
 network-interface = Network Interface
+automatic = Automatic
+aggregate-interfaces = Sum All Interfaces
+include-virtual-interfaces = Include Virtual Interfaces
+prefer-vpn = Prefer VPN
+link-preference = Preferred Connection
+any = Any
+wired = Wired
+wireless = Wireless
+all-interfaces = All
+excluded-interfaces = Excluded Interfaces
+exclusion-pattern = Pattern, e.g. veth*
+add = Add
+panel-interfaces = Shown Side by Side
+interface-rates = Traffic by Interface
+overview = Overview
+settings = Settings
+usage = Usage
+monitor = Monitor
+download = Download
+upload = Upload
+since-boot = Since Boot
+this-session = This Session
 unit = Data Unit
 bits = Bits
 bytes = Bytes
+packets = Packets
 update-rate = Update Rate
+peak = Peak
+minimum = Minimum
+maximum = Maximum
+average = Average
+show-peak = Show Peak Rates
+peak-window = Peak Over
+session-peak-download = Highest download of the session
+session-peak-upload = Highest upload of the session
+show-sparkline = Show Graph in Panel
+sparkline-length = Graph Length
+graph-log-scale = Logarithmic Graph Scale
+graph-style = Graph Style
+lines = Lines
+stacked = Stacked Areas
+update-count = { $count ->
+    [1] 1 update
+   *[other] { $count } updates
+}
+history-retention = Keep History For
+minute-count = { $count ->
+    [1] 1 minute
+   *[other] { $count } minutes
+}
+hour-count = { $count ->
+    [1] 1 hour
+   *[other] { $count } hours
+}
+smoothing = Smoothing
+smoothing-samples = { $count ->
+    [1] Off
+   *[other] { $count } updates
+}
+minimum-rate = Show as 0 Below
+hide-below-minimum = Leave Blank Instead of 0
 show-download-speed = Show Download Speed
 show-upload-speed = Show Upload Speed
+compact = Show Only the Busier Direction
+upload-first = Show Upload First
+tabular-digits = Fixed-Width Digits
+show-unit = Show Units
+download-symbol = Download Symbol
+upload-symbol = Upload Symbol
+symbol-before-value = Symbol Before Value
 second-short = s
+millisecond-short = ms
 kilo-short = K
+kilo-short-si = k
 mega-short = M
+giga-short = G
+tera-short = T
+kibi-short = Ki
+mebi-short = Mi
+gibi-short = Gi
+tebi-short = Ti
 bytes-short = B
 bits-short = b
+packets-per-second-short = pps
+packets-short = pkt
+wake-on-lan = Wake-on-LAN
+wake-device = Wake Device
+interfaces = Interfaces
+reconnect = Reconnect
+listening-ports = Listening Ports ({ $count })
+show = Show
+hide = Hide
+firewall = Firewall
+inactive = Inactive
+shaping-limit = Shaping Limit
+limit-upload = Limit Upload
+apply = Apply
+remove = Remove
+show-reachability = Show Internet Reachability
+reachability-target = Reachability Target
+stats-backend = Statistics Source
+stats-backend-netlink = Netlink
+stats-backend-sysfs = sysfs
+stats-backend-proc-net-dev = /proc/net/dev
+event-command = Event Command
+event-command-description = Run through sh on interface changes, roaming and data cap warnings
+webhook-url = Webhook URL
+wake-on-lan-mac = Wake-on-LAN MAC Address
+alias = Alias
+interface-label = Interface Label
+none = None
+name = Name
+abbreviation = Abbreviation
+interface-coloring = Interface Color
+text = Text
+dot = Dot
+not-connected = Not Connected
+access-point = Access Point
+roamed = { $interface } roamed to another access point
+interface-changed = Now monitoring { $interface }
+link-local-only = Only a link-local address is assigned, the network is not configured
+gateway = Gateway ({ $address })
+internet = Internet
+reachable = Reachable
+unreachable = Unreachable
+unknown = Unknown
+download-unit = Download Unit
+upload-unit = Upload Unit
+show-both-units = Show Bits and Bytes
+unit-scale = Prefixes
+unit-scale-iec = Binary (1024)
+unit-scale-si = Decimal (1000)
+iec-labels = Binary Prefix Labels (KiB, MiB)
+fixed-prefix = Fixed Prefix
+display-mode = Panel Shows
+rates = Rates
+session-totals = Session Totals
+daily-totals = Daily Totals
+today = Today
+yesterday = Yesterday
+this-month = This Month
+today-vs-last-week = Today vs a Week Ago
+this-month-vs-last = This Month vs the Same Days of Last
+usage-change = { $change } % vs { $previous }
+history = History
+export = Export…
+export-history = Export History
+save-graph = Save Graph…
+usage-retention = Keep Usage For
+import = Import…
+import-vnstat = Import vnStat Database
+vnstat-imported = { $count ->
+    [0] Nothing older than the recorded usage was found
+    [1] 1 day or month of usage was imported
+   *[other] { $count } days and months of usage were imported
+}
+day-count = { $count ->
+    [1] 1 day
+   *[other] { $count } days
+}
+billing-day = Month Starts On Day
+data-cap = Monthly Data Cap
+data-cap-remaining = { $remaining } left of { $cap }
+data-cap-thresholds = Warn at Percentages
+data-cap-crossed = { $percent }% of the monthly data cap used
+off = Off
+reset = Reset
+kilo = Kilo
+mega = Mega
+giga = Giga
+tera = Tera
+palette = Colors
+palette-theme = Theme
+palette-red-green-safe = Deuteranopia / Protanopia
+palette-blue-yellow-safe = Tritanopia
+current-rates = Current Rates
+announce = Read Aloud
+announce-shortcut = Ctrl+R reads them aloud while this popup is open
+announcement = Download { $download } per second, upload { $upload } per second
+spoken-kilo = kilo
+spoken-mega = mega
+spoken-giga = giga
+spoken-tera = tera
+spoken-bits = bits
+spoken-bytes = bytes
+spoken-packets = packets
+usage-by-hour = Usage by Hour of the Week
+busiest-hour = Busiest Hour
+this-month-by-interface = This Month by Interface
+no-usage = No usage recorded yet
+weekday = { $day ->
+    [1] Mon
+    [2] Tue
+    [3] Wed
+    [4] Thu
+    [5] Fri
+    [6] Sat
+   *[other] Sun
+}
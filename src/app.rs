@@ -1,7 +1,27 @@
 use {
     crate::{
-        config::{BitrateAppletConfig, Unit},
-        fl, network,
+        config::{
+            BitrateAppletConfig, DisplayMode, GraphStyle, InterfaceColoring, InterfaceLabel,
+            LinkPreference, MAX_UPDATE_RATE, MIN_UPDATE_RATE, Palette, StatsBackend, Unit,
+            UnitPrefix, UnitScale,
+        },
+        database::{Heatmap, UsageDatabase},
+        events::{self, Event},
+        export,
+        firewall::{self, FirewallStatus},
+        fl, graph,
+        history::{self, History, Sample},
+        locale, netlink, network,
+        nm::{self, Device},
+        notification, palette, persistence,
+        plugin::{self, DataSource, Reading},
+        ports::{self, ListeningPort},
+        reachability,
+        shaping::{self, ShapingLimit},
+        speech,
+        stats::{self, Counters, StatsSource},
+        usage::{self, DailyUsage, UsageAccounting},
+        vnstat,
     },
     cosmic::{
         self, Element,
@@ -9,9 +29,11 @@ use {
         config::{CosmicTk, FontConfig},
         cosmic_config::{self, Config, CosmicConfigEntry},
         cosmic_theme::Spacing,
+        dialog::file_chooser,
         iced::{
             self, Alignment, Limits, Rectangle, Subscription,
             advanced::graphics::text::cosmic_text::{self, Buffer, FontSystem, Metrics, Shaping},
+            futures::{SinkExt, Stream},
             widget::{column, row},
             window,
         },
@@ -29,14 +51,330 @@ use {
             segmented_button, segmented_control, spin_button, toggler,
         },
     },
-    std::sync::LazyLock,
-    tokio,
+    std::{
+        collections::{BTreeMap, VecDeque},
+        net::{IpAddr, Ipv4Addr},
+        path::{Path, PathBuf},
+        sync::{Arc, LazyLock},
+        time::Instant,
+    },
+    tokio::{self, signal::unix::SignalKind},
 };
 
 static AUTOSIZE_MAIN_ID: LazyLock<widget::Id> = LazyLock::new(|| widget::Id::new("autosize-main"));
 static AUTOSIZE_ICON_BTN_ID: LazyLock<widget::Id> =
     LazyLock::new(|| widget::Id::new("autosize-icon-btn"));
 
+/// Number of roaming events kept for the popup
+const ROAMING_LOG_LENGTH: usize = 5;
+
+/// Seconds between two polls of the plugin data sources
+const PLUGIN_POLL_INTERVAL: u64 = 5;
+
+/// Digits of the widest value with the closest prefix, e.g. 999.9
+const VALUE_DIGITS: usize = 4;
+
+/// Integer digits a value keeps under a fixed prefix, larger ones move on to
+/// the next prefix so they fit the room measured for them
+const FIXED_PREFIX_DIGITS: usize = 6;
+
+/// Seconds between two saves of the state, which is also saved on exit
+const SAVE_INTERVAL: u64 = 300;
+
+/// Milliseconds the interface name stays in the panel after scrolling to it
+const LABEL_FLASH_DURATION: u64 = 1500;
+
+/// Pixels of smooth scrolling that count as one step of the wheel
+const SCROLL_STEP_PIXELS: f32 = 20.0;
+
+/// Seconds of history offered in the settings
+const HISTORY_RETENTIONS: [u32; 7] = [
+    60,
+    600,
+    3600,
+    6 * 3600,
+    24 * 3600,
+    7 * 24 * 3600,
+    30 * 24 * 3600,
+];
+
+/// Width of the panel graph relative to the line height
+const SPARKLINE_ASPECT_RATIO: f32 = 3.0;
+
+/// Seconds the popup graph can be set to span
+const GRAPH_RANGES: [u32; 4] = [60, 600, 3600, 24 * 3600];
+
+/// Factor the popup graph range changes by at every step of the wheel
+const GRAPH_ZOOM_STEP: f32 = 1.25;
+
+/// Size of the popup graph in pixels
+const GRAPH_WIDTH: f32 = 280.0;
+const GRAPH_HEIGHT: f32 = 80.0;
+
+/// Size of the cells of the usage heatmap in pixels
+const HEATMAP_CELL: f32 = 14.0;
+
+/// `seconds` in whole minutes, hours or days.
+fn format_duration(seconds: u32) -> String {
+    match seconds {
+        ..3600 => fl!("minute-count", count = seconds / 60),
+        ..86400 => fl!("hour-count", count = seconds / 3600),
+        _ => fl!("day-count", count = seconds / 86400),
+    }
+}
+
+/// Refreshes the interfaces as soon as the kernel reports a link or address
+/// change. The periodic refresh stays as a fallback and for the state netlink
+/// does not report, such as roaming.
+fn link_changes() -> impl Stream<Item = Message> {
+    iced::stream::channel(1, async |mut output| {
+        let monitor = match netlink::LinkMonitor::new() {
+            Ok(monitor) => Arc::new(monitor),
+            Err(why) => {
+                eprintln!("error while watching network links: {why}");
+                return;
+            }
+        };
+        loop {
+            let link_monitor = monitor.clone();
+            match tokio::task::spawn_blocking(move || link_monitor.wait()).await {
+                Ok(Ok(())) => {
+                    if output.send(Message::UpdateNetworkInterfaces).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(Err(why)) => {
+                    eprintln!("error while watching network links: {why}");
+                    return;
+                }
+                Err(_) => return,
+            }
+        }
+    })
+}
+
+/// Reports the termination the panel asks for when it stops or restarts the
+/// applet, so the state can be saved before exiting.
+fn termination() -> impl Stream<Item = Message> {
+    iced::stream::channel(1, async |mut output| {
+        let mut terminate = match tokio::signal::unix::signal(SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(why) => {
+                eprintln!("error while watching for termination: {why}");
+                return;
+            }
+        };
+        if terminate.recv().await.is_some() {
+            let _ = output.send(Message::Terminate).await;
+        }
+    })
+}
+
+/// Traffic counted since the applet started or the totals were reset.
+#[derive(Debug, Default, Clone, Copy)]
+struct SessionTotals {
+    received_bytes: u64,
+    sent_bytes: u64,
+    received_packets: u64,
+    sent_packets: u64,
+}
+
+/// Adds `sample` to `samples`, keeping the newest `count` of them, and
+/// returns their average.
+fn moving_average(
+    samples: &mut VecDeque<(u64, u64)>,
+    sample: (u64, u64),
+    count: usize,
+) -> (u64, u64) {
+    samples.push_back(sample);
+    while samples.len() > count.max(1) {
+        samples.pop_front();
+    }
+    let (bytes, packets) = samples.iter().fold((0, 0), |(bytes, packets), sample| {
+        (bytes + sample.0, packets + sample.1)
+    });
+    let count = samples.len() as u64;
+    (bytes / count, packets / count)
+}
+
+/// Byte and packet counters of an interface and the speeds measured from them.
+struct InterfaceCounters {
+    /// Kernel index of the interface the counters were read from
+    ifindex: Option<u32>,
+    /// Counters of the last update, `None` while the interface is down
+    received_bytes: Option<u64>,
+    sent_bytes: Option<u64>,
+    received_packets: Option<u64>,
+    sent_packets: Option<u64>,
+    /// When the counters were last read
+    read_at: Instant,
+    /// Speeds in bytes per second
+    download_speed: u64,
+    upload_speed: u64,
+    /// Speeds in packets per second
+    download_packet_speed: u64,
+    upload_packet_speed: u64,
+    /// Latest measured speeds in bytes and packets per second, averaged into the speeds
+    download_samples: VecDeque<(u64, u64)>,
+    upload_samples: VecDeque<(u64, u64)>,
+    session_totals: SessionTotals,
+    /// Bytes received and sent since boot, from the counters at the first
+    /// read on, kept when the interface is recreated
+    since_boot: (u64, u64),
+    usage: UsageAccounting,
+}
+
+impl InterfaceCounters {
+    fn new(stats: &dyn StatsSource, network_interface: &str) -> Self {
+        InterfaceCounters::from_reading(
+            network::get_interface_index(network_interface),
+            stats.counters(network_interface),
+        )
+    }
+
+    /// Starts measuring from `counters`, read from the interface `ifindex`
+    fn from_reading(ifindex: Option<u32>, counters: Option<Counters>) -> Self {
+        InterfaceCounters {
+            ifindex,
+            received_bytes: counters.map(|counters| counters.received_bytes),
+            sent_bytes: counters.map(|counters| counters.sent_bytes),
+            received_packets: counters.map(|counters| counters.received_packets),
+            sent_packets: counters.map(|counters| counters.sent_packets),
+            read_at: Instant::now(),
+            download_speed: 0,
+            upload_speed: 0,
+            download_packet_speed: 0,
+            upload_packet_speed: 0,
+            download_samples: VecDeque::new(),
+            upload_samples: VecDeque::new(),
+            session_totals: SessionTotals::default(),
+            since_boot: counters
+                .map(|counters| (counters.received_bytes, counters.sent_bytes))
+                .unwrap_or_default(),
+            usage: UsageAccounting::default(),
+        }
+    }
+
+    /// Reads the counters, returning the bytes received and sent since the
+    /// last update
+    fn update(
+        &mut self,
+        stats: &dyn StatsSource,
+        network_interface: &str,
+        smoothing: usize,
+    ) -> (u64, u64) {
+        self.add_reading(
+            stats.counters(network_interface),
+            stats.counter_limit(),
+            Instant::now(),
+            smoothing,
+        )
+    }
+
+    /// Starts over if another interface took the name since the latest
+    /// check, as its counters are unrelated. Checked when links change only,
+    /// not on every update.
+    fn check_ifindex(&mut self, stats: &dyn StatsSource, network_interface: &str) {
+        let ifindex = network::get_interface_index(network_interface);
+        if ifindex != self.ifindex {
+            self.ifindex = ifindex;
+            self.reset(stats.counters(network_interface));
+        }
+    }
+
+    /// Measures the speeds and totals since the previous reading from
+    /// `counters`, which wrap around at `limit`, read at `read_at`. Returns
+    /// the bytes received and sent in between.
+    fn add_reading(
+        &mut self,
+        counters: Option<Counters>,
+        limit: Option<u64>,
+        read_at: Instant,
+        smoothing: usize,
+    ) -> (u64, u64) {
+        let elapsed = read_at - self.read_at;
+        let received_bytes = counters.map(|counters| counters.received_bytes);
+        let sent_bytes = counters.map(|counters| counters.sent_bytes);
+        let received_packets = counters.map(|counters| counters.received_packets);
+        let sent_packets = counters.map(|counters| counters.sent_packets);
+        // Interfaces that went down or came back have no previous reading
+        let delta = |current: Option<u64>, previous: Option<u64>| match (current, previous) {
+            (Some(current), Some(previous)) => stats::counter_delta(current, previous, limit),
+            _ => 0,
+        };
+        let received = delta(received_bytes, self.received_bytes);
+        let sent = delta(sent_bytes, self.sent_bytes);
+        let received_count = delta(received_packets, self.received_packets);
+        let sent_count = delta(sent_packets, self.sent_packets);
+        (self.download_speed, self.download_packet_speed) = moving_average(
+            &mut self.download_samples,
+            (
+                stats::per_second(received, elapsed),
+                stats::per_second(received_count, elapsed),
+            ),
+            smoothing,
+        );
+        (self.upload_speed, self.upload_packet_speed) = moving_average(
+            &mut self.upload_samples,
+            (
+                stats::per_second(sent, elapsed),
+                stats::per_second(sent_count, elapsed),
+            ),
+            smoothing,
+        );
+        self.session_totals.received_bytes += received;
+        self.session_totals.sent_bytes += sent;
+        self.session_totals.received_packets += received_count;
+        self.session_totals.sent_packets += sent_count;
+        self.since_boot.0 += received;
+        self.since_boot.1 += sent;
+        self.usage.record(received, sent);
+        self.received_bytes = received_bytes;
+        self.sent_bytes = sent_bytes;
+        self.received_packets = received_packets;
+        self.sent_packets = sent_packets;
+        self.read_at = read_at;
+        (received, sent)
+    }
+
+    /// Measures from `counters` on, forgetting the previous speeds
+    fn reset(&mut self, counters: Option<Counters>) {
+        *self = InterfaceCounters {
+            session_totals: self.session_totals,
+            since_boot: self.since_boot,
+            usage: std::mem::take(&mut self.usage),
+            ..InterfaceCounters::from_reading(self.ifindex, counters)
+        };
+    }
+}
+
+/// Pages of the popup.
+#[derive(Debug, Clone, PartialEq)]
+enum PopupTab {
+    /// Rates, totals and details of one interface
+    Interface(String),
+    /// Traffic of every interface and the state of the system
+    Overview,
+    /// Usage by weekday and hour
+    Usage,
+    Settings,
+}
+
+/// Rates of one of the interfaces shown side by side in the panel.
+struct PanelInterface {
+    name: String,
+    label: String,
+    label_width: f32,
+    counters: InterfaceCounters,
+    download_speed_display: String,
+    download_unit: String,
+    /// Download in the counterpart unit, when both bits and bytes are shown
+    download_counterpart: Option<(String, String)>,
+    upload_speed_display: String,
+    upload_unit: String,
+    upload_counterpart: Option<(String, String)>,
+}
+
 pub struct AppModel {
     /// Application state which is managed by the COSMIC runtime
     core: cosmic::Core,
@@ -50,30 +388,144 @@ pub struct AppModel {
     network_interfaces: Vec<String>,
     /// Selected network interface
     selected_network_interface: Option<usize>,
-    /// Received bytes
-    received_bytes: u64,
-    /// Sent bytes
-    sent_bytes: u64,
-    /// Download speed
-    download_speed: u64,
+    /// Kernel index of the selected interface, to follow it across renames
+    selected_ifindex: Option<u32>,
+    /// Counters, speeds and traffic of the monitored interfaces, either the
+    /// selected one or all of them summed
+    monitored: InterfaceCounters,
+    /// Download and upload speeds of the peak window, as bytes and packets per second
+    peak_samples: VecDeque<((u64, u64), (u64, u64))>,
+    /// Where the traffic of the monitored interfaces is kept for the long term
+    usage_database: Option<UsageDatabase>,
+    /// Traffic of every interface seen per day, kept when it goes down
+    interface_usage: BTreeMap<String, UsageAccounting>,
+    /// Usage by weekday and hour, read when the usage tab is shown
+    heatmap: Option<Heatmap>,
+    /// Speeds of the monitored interfaces at the latest updates
+    history: History,
+    /// Updates with the highest download and upload speeds of the session
+    session_peaks: (Option<Sample>, Option<Sample>),
+    /// When the monitored interface changed and the one it changed to,
+    /// marked on the popup graph
+    interface_changes: VecDeque<(chrono::DateTime<chrono::Local>, Option<String>)>,
     download_speed_display: String,
     download_unit: String,
-    /// Upload speed
-    upload_speed: u64,
+    /// Download in the counterpart unit, when both bits and bytes are shown
+    download_counterpart: Option<(String, String)>,
     upload_speed_display: String,
     upload_unit: String,
+    upload_counterpart: Option<(String, String)>,
+    /// Interfaces shown side by side in the panel
+    panel_interfaces: Vec<PanelInterface>,
+    /// Counters of every active interface, listed in the popup
+    interface_counters: Vec<(String, InterfaceCounters)>,
     /// Unit model
     unit_model: segmented_button::SingleSelectModel,
     /// Bits Entity
     bits_entity: segmented_button::Entity,
     /// Bytes Entity
     bytes_entity: segmented_button::Entity,
+    /// Packets Entity
+    packets_entity: segmented_button::Entity,
+    /// Upload unit model
+    upload_unit_model: segmented_button::SingleSelectModel,
+    /// Upload Bits Entity
+    upload_bits_entity: segmented_button::Entity,
+    /// Upload Bytes Entity
+    upload_bytes_entity: segmented_button::Entity,
+    /// Upload Packets Entity
+    upload_packets_entity: segmented_button::Entity,
+    /// Popup tabs, one per active interface followed by the overview and settings
+    popup_tabs: segmented_button::SingleSelectModel,
     rectangle_tracker: Option<RectangleTracker<u32>>,
     rectangle: Rectangle,
     font_system: FontSystem,
     unit_width: f32,
     data_width: f32,
     line_height: f32,
+    dot_width: f32,
+    warning_width: f32,
+    today_width: f32,
+    /// Whether the monitored interface only has link-local addresses
+    link_local_only: bool,
+    interface_font: FontConfig,
+    /// Label of the monitored interface shown in the panel
+    interface_label: String,
+    interface_label_width: f32,
+    /// Number of pending label flashes, the interface name is shown while
+    /// there are any
+    label_flashes: u32,
+    /// Scrolled distance not yet turned into an interface switch
+    scroll_offset: f32,
+    /// Interface switched to by scrolling over the applet, shown while it is
+    /// up without changing the configured one
+    scrolled_interface: Option<String>,
+    /// Interface label dropdown options
+    interface_label_options: Vec<String>,
+    /// Unit scale dropdown options
+    unit_scale_options: Vec<String>,
+    /// Display mode dropdown options
+    display_mode_options: Vec<String>,
+    /// Fixed prefix dropdown options
+    fixed_prefix_options: Vec<String>,
+    /// History retention dropdown options
+    history_retention_options: Vec<String>,
+    /// Interface coloring dropdown options
+    interface_coloring_options: Vec<String>,
+    /// Graph style dropdown options
+    graph_style_options: Vec<String>,
+    /// Palette dropdown options
+    palette_options: Vec<String>,
+    /// Link preference dropdown options
+    link_preference_options: Vec<String>,
+    /// Statistics backend dropdown options
+    stats_backend_options: Vec<String>,
+    /// Result of the latest reachability probe
+    online: Option<bool>,
+    /// Devices managed by NetworkManager, refreshed while the popup is open
+    devices: Vec<Device>,
+    /// Active firewall backend, refreshed while the popup is open
+    firewall_status: Option<FirewallStatus>,
+    /// tc rate limit on the monitored interface, refreshed while the popup is open
+    shaping_limit: Option<ShapingLimit>,
+    /// Whether a shaping limit is being applied or removed
+    applying_shaping_limit: bool,
+    /// Locally listening ports, refreshed while the popup is open
+    listening_ports: Vec<ListeningPort>,
+    /// Pattern being typed in the popup before it is excluded
+    exclusion_pattern: String,
+    /// Data cap percentages as typed in the popup
+    data_cap_thresholds_input: String,
+    /// Start of the monthly cycle and highest data cap percentage notified
+    /// about in it
+    data_cap_notified: Option<(chrono::NaiveDate, u8)>,
+    /// Horizontal position of the pointer over the popup graph
+    graph_hover: Option<f32>,
+    /// Seconds spanned by the popup graph
+    graph_range: u32,
+    /// Whether the listening ports list is expanded
+    show_listening_ports: bool,
+    /// Wi-Fi access point of the monitored interface, with the interface it was read from
+    bssid: Option<(String, String)>,
+    /// Recent roaming events, newest first
+    roaming_log: Vec<String>,
+    /// Default gateway of the monitored interface and whether it answered a ping
+    gateway: Option<(Ipv4Addr, Option<bool>)>,
+    /// NetworkManager connection active on the monitored interface
+    connection_name: Option<String>,
+    /// Whether a reconnect of the monitored interface is in progress
+    reconnecting: bool,
+    /// OTLP metrics exporter
+    #[cfg(feature = "otlp")]
+    telemetry: Option<crate::telemetry::Telemetry>,
+    /// Where the interfaces and their counters are read from
+    stats: Box<dyn StatsSource>,
+    /// Plugin data sources
+    data_sources: Vec<Arc<dyn DataSource>>,
+    /// Latest readings of each data source, keyed by source name
+    plugin_readings: Vec<(String, Vec<Reading>)>,
+    /// Whether a poll of the data sources is still running
+    polling_plugins: bool,
 }
 
 /// Messages emitted by the application and its widgets.
@@ -84,28 +536,418 @@ pub enum Message {
     UpdateConfig(BitrateAppletConfig),
     UpdateBandwidth,
     UpdateNetworkInterfaces,
+    SaveState,
+    Terminate,
+    Export,
+    ExportTo(Option<PathBuf>),
+    SaveGraph,
+    SaveGraphTo(Option<PathBuf>),
+    ImportVnstat,
+    ImportVnstatFrom(Option<PathBuf>),
+    GraphHovered(Option<f32>),
+    GraphRangeChanged(u32),
+    GraphZoomed(iced::mouse::ScrollDelta),
     UpdateSelectedNetworkInterface(usize),
     UnitChanged(segmented_button::Entity),
-    UpdateRateChanged(u8),
+    UploadUnitChanged(segmented_button::Entity),
+    ShowBothUnitsChanged(bool),
+    UnitScaleChanged(usize),
+    IecLabelsChanged(bool),
+    FixedPrefixChanged(usize),
+    DisplayModeChanged(usize),
+    ResetSessionTotals,
+    UpdateRateChanged(u32),
+    SmoothingChanged(u8),
+    ShowPeakChanged(bool),
+    PeakWindowChanged(u16),
+    ShowSparklineChanged(bool),
+    SparklineLengthChanged(u16),
+    GraphLogScaleChanged(bool),
+    GraphStyleChanged(usize),
+    HistoryRetentionChanged(usize),
+    BillingDayChanged(u8),
+    DataCapChanged(u32),
+    UsageRetentionChanged(u32),
+    DataCapThresholdsInput(String),
+    MinimumRateChanged(u32),
+    HideBelowMinimumChanged(bool),
     ShowDownloadSpeedChanged(bool),
     ShowUploadSpeedChanged(bool),
+    CompactChanged(bool),
+    UploadFirstChanged(bool),
+    TabularDigitsChanged(bool),
+    ShowUnitChanged(bool),
+    DownloadSymbolChanged(String),
+    UploadSymbolChanged(String),
+    SymbolBeforeValueChanged(bool),
+    ShowReachabilityChanged(bool),
+    ReachabilityTargetChanged(String),
+    StatsBackendChanged(usize),
+    EventCommandChanged(String),
+    WebhookUrlChanged(String),
+    WakeOnLanMacChanged(String),
+    InterfaceAliasChanged(String, String),
+    AggregateInterfacesChanged(bool),
+    IncludeVirtualInterfacesChanged(bool),
+    PreferVpnChanged(bool),
+    LinkPreferenceChanged(usize),
+    ExclusionPatternInput(String),
+    AddExclusionPattern,
+    RemoveExclusionPattern(usize),
+    InterfaceLabelChanged(usize),
+    InterfaceColoringChanged(usize),
+    PaletteChanged(usize),
+    PanelInterfaceToggled(String, bool),
+    Scrolled(iced::mouse::ScrollDelta),
+    EndLabelFlash,
+    ProbeReachability,
+    Announce,
+    ReachabilityUpdated(bool),
+    WakeOnLan,
+    DevicesUpdated(Vec<Device>),
+    ConnectionNameUpdated(Option<String>),
+    GatewayUpdated(Option<(Ipv4Addr, Option<bool>)>),
+    BssidUpdated(String, Option<String>),
+    SetDeviceConnected(String, bool),
+    FirewallStatusUpdated(Option<FirewallStatus>),
+    ShapingLimitUpdated(Option<ShapingLimit>),
+    ShapingRateChanged(u32),
+    SetShapingLimit(Option<u32>),
+    ShapingLimitSet,
+    ListeningPortsUpdated(Vec<ListeningPort>),
+    ToggleListeningPorts,
+    Reconnect,
+    Reconnected,
+    PollPlugins,
+    PluginsPolled(Vec<(String, Vec<Reading>)>),
     Rectangle(RectangleUpdate<u32>),
     ThemeChanged(cosmic::config::CosmicTk),
+    PopupTabActivated(segmented_button::Entity),
     Surface(surface::Action),
 }
 
 impl AppModel {
-    fn select_default_network_interface(&mut self) {
-        self.selected_network_interface = None;
-        self.received_bytes = 0;
-        self.sent_bytes = 0;
-        if let Some(interface) = self.network_interfaces.get(0) {
-            self.selected_network_interface = Some(0);
-            self.received_bytes = network::get_received_bytes(interface).unwrap_or(0);
-            self.sent_bytes = network::get_sent_bytes(interface).unwrap_or(0);
+    /// Lists the active interfaces that are not excluded in the config
+    fn get_network_interfaces(&self) -> Vec<String> {
+        let mut network_interfaces = self.stats.interfaces();
+        network_interfaces.retain(|network_interface| {
+            !network::is_excluded(network_interface, &self.config.excluded_interfaces)
+                && (self.config.include_virtual_interfaces
+                    || !network::is_virtual(network_interface)
+                    || (self.config.prefer_vpn && network::is_vpn(network_interface)))
+        });
+        network_interfaces
+    }
+
+    fn select_network_interface(&mut self, index: Option<usize>) {
+        self.selected_network_interface =
+            index.filter(|index| *index < self.network_interfaces.len());
+        self.selected_ifindex = self
+            .selected_network_interface_name()
+            .and_then(|network_interface| network::get_interface_index(&network_interface));
+        self.reset_byte_counters();
+    }
+
+    /// Counters of the monitored traffic
+    fn get_counters(&self) -> Option<Counters> {
+        if self.config.aggregate_interfaces {
+            Some(stats::total_counters(
+                self.stats.as_ref(),
+                &self.network_interfaces,
+            ))
+        } else {
+            self.stats
+                .counters(&self.selected_network_interface_name()?)
+        }
+    }
+
+    /// Starts measuring the monitored traffic from its current counters
+    fn reset_byte_counters(&mut self) {
+        let counters = self.get_counters();
+        self.monitored.reset(counters);
+        self.peak_samples.clear();
+    }
+
+    /// Remembers the current speeds for the peak window
+    fn record_peak_sample(&mut self) {
+        self.peak_samples.push_back((
+            (
+                self.monitored.download_speed,
+                self.monitored.download_packet_speed,
+            ),
+            (
+                self.monitored.upload_speed,
+                self.monitored.upload_packet_speed,
+            ),
+        ));
+        let length = (self.config.peak_window as u128 * 1000
+            / self.config.get_update_interval().as_millis())
+        .max(1);
+        while self.peak_samples.len() as u128 > length {
+            self.peak_samples.pop_front();
+        }
+    }
+
+    /// Formats the highest download and upload of the peak window
+    fn format_peaks(&self) -> ((String, String), (String, String)) {
+        let max =
+            |speeds: (u64, u64), speed: (u64, u64)| (speeds.0.max(speed.0), speeds.1.max(speed.1));
+        let (download, upload) = self
+            .peak_samples
+            .iter()
+            .fold(((0, 0), (0, 0)), |(download, upload), sample| {
+                (max(download, sample.0), max(upload, sample.1))
+            });
+        let network_interface = self.get_monitored_interface();
+        let download_unit = self
+            .config
+            .get_download_unit_for(network_interface.as_deref());
+        let upload_unit = self
+            .config
+            .get_upload_unit_for(network_interface.as_deref());
+        (
+            self.format_rate(
+                download_unit.convert(download.0, download.1),
+                download_unit,
+                &self.config.download_symbol,
+            ),
+            self.format_rate(
+                upload_unit.convert(upload.0, upload.1),
+                upload_unit,
+                &self.config.upload_symbol,
+            ),
+        )
+    }
+
+    /// Selects the interface scrolled to or the configured one when it is up,
+    /// otherwise the first
+    /// entry of the priority list that is up, a VPN tunnel if preferred, the
+    /// first wired or wireless interface if preferred, the one carrying the
+    /// default route, the current one or the first one.
+    fn select_preferred_network_interface(&mut self) {
+        let position = |name: &String| {
+            self.network_interfaces
+                .iter()
+                .position(|interface| interface == name)
+        };
+        let scrolled = self.scrolled_interface.as_ref().and_then(position);
+        let preferred = self.config.network_interface.as_ref().and_then(position);
+        let prioritized = self.config.interface_priority.iter().find_map(position);
+        let vpn = self
+            .network_interfaces
+            .iter()
+            .position(|network_interface| network::is_vpn(network_interface))
+            .filter(|_| self.config.prefer_vpn);
+        let link = self
+            .network_interfaces
+            .iter()
+            .position(|network_interface| match self.config.link_preference {
+                LinkPreference::Any => false,
+                LinkPreference::Wired => {
+                    !network::is_wireless(network_interface)
+                        && !network::is_virtual(network_interface)
+                }
+                LinkPreference::Wireless => network::is_wireless(network_interface),
+            });
+        let default_route = network::get_default_route_interfaces()
+            .iter()
+            .find_map(position);
+        // Found by kernel index so that a renamed interface stays selected
+        let current = self.selected_ifindex.and_then(|ifindex| {
+            self.network_interfaces
+                .iter()
+                .position(|network_interface| {
+                    network::get_interface_index(network_interface) == Some(ifindex)
+                })
+        });
+        match scrolled
+            .or(preferred)
+            .or(prioritized)
+            .or(vpn)
+            .or(link)
+            .or(default_route)
+            .or(current)
+        {
+            // Keep the counters of an interface that is still selected
+            Some(index) if Some(index) == current => {
+                self.selected_network_interface = Some(index);
+            }
+            Some(index) => self.select_network_interface(Some(index)),
+            None => self.select_network_interface(Some(0)),
+        }
+    }
+
+    /// The alias of `network_interface`, or its name when it has none
+    fn get_interface_display_name(&self, network_interface: &str) -> String {
+        self.config
+            .interface_aliases
+            .get(network_interface)
+            .filter(|alias| !alias.is_empty())
+            .cloned()
+            .unwrap_or_else(|| network_interface.to_string())
+    }
+
+    /// Display name of the monitored traffic, covering all interfaces when
+    /// they are summed
+    fn get_monitored_display_name(&self) -> Option<String> {
+        if self.config.aggregate_interfaces {
+            Some(fl!("all-interfaces"))
+        } else {
+            self.selected_network_interface_name()
+                .map(|network_interface| self.get_interface_display_name(&network_interface))
+        }
+    }
+
+    /// The single interface whose traffic is shown, `None` when all are summed
+    fn get_monitored_interface(&self) -> Option<String> {
+        self.selected_network_interface_name()
+            .filter(|_| !self.config.aggregate_interfaces)
+    }
+
+    fn selected_network_interface_name(&self) -> Option<String> {
+        self.selected_network_interface
+            .and_then(|index| self.network_interfaces.get(index))
+            .cloned()
+    }
+
+    fn emit(&self, event: Event) {
+        if !self.config.event_command.is_empty()
+            && self.config.hooked_events.contains(&event.kind())
+        {
+            events::run_hook(&self.config.event_command, &event);
+        }
+        if !self.config.webhook_url.is_empty() && self.config.webhook_events.contains(&event.kind())
+        {
+            events::send_webhook(
+                &self.config.webhook_url,
+                &self.config.webhook_template,
+                &event,
+            );
+        }
+    }
+
+    /// Emits [`Event::InterfaceChanged`] if the selection moved away from
+    /// `previous_interface`, and keeps it for the popup graph
+    fn emit_interface_changed(&mut self, previous_interface: Option<String>) {
+        let current_interface = self.selected_network_interface_name();
+        if current_interface != previous_interface {
+            let now = chrono::Local::now();
+            let oldest = now - chrono::TimeDelta::seconds(self.config.history_retention as i64);
+            self.interface_changes.retain(|(time, _)| *time >= oldest);
+            self.interface_changes
+                .push_back((now, current_interface.clone()));
+            self.emit(Event::InterfaceChanged {
+                from: previous_interface,
+                to: current_interface,
+            });
         }
     }
 
+    /// Shows the name of the monitored interface in the panel for a moment
+    fn flash_interface_label(&mut self) -> cosmic::Task<cosmic::Action<Message>> {
+        self.label_flashes += 1;
+        self.update_interface_label();
+        cosmic::Task::perform(
+            tokio::time::sleep(tokio::time::Duration::from_millis(LABEL_FLASH_DURATION)),
+            |_| cosmic::Action::App(Message::EndLabelFlash),
+        )
+    }
+
+    fn update_link_local_only(&mut self) {
+        self.link_local_only = self
+            .selected_network_interface_name()
+            .is_some_and(|interface| network::has_only_link_local_addresses(&interface));
+    }
+
+    fn update_bssid(&self) -> cosmic::Task<cosmic::Action<Message>> {
+        let Some(network_interface) = self
+            .selected_network_interface_name()
+            .filter(|interface| network::is_wireless(interface))
+        else {
+            return cosmic::Task::none();
+        };
+        cosmic::Task::perform(
+            async move {
+                let bssid = {
+                    let network_interface = network_interface.clone();
+                    tokio::task::spawn_blocking(move || nm::get_bssid(&network_interface))
+                        .await
+                        .unwrap_or_default()
+                };
+                (network_interface, bssid)
+            },
+            |(network_interface, bssid)| {
+                cosmic::Action::App(Message::BssidUpdated(network_interface, bssid))
+            },
+        )
+    }
+
+    /// Refreshes the details that are only shown in the popup
+    fn update_popup_details(&self) -> cosmic::Task<cosmic::Action<Message>> {
+        let network_interface = self.selected_network_interface_name();
+        let connection_interface = network_interface.clone();
+        let gateway_interface = network_interface.clone();
+        cosmic::Task::batch([
+            cosmic::Task::perform(
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        let gateway = network::get_default_gateway(&gateway_interface?)?;
+                        Some((gateway, network::ping(IpAddr::V4(gateway))))
+                    })
+                    .await
+                    .unwrap_or_default()
+                },
+                |gateway| cosmic::Action::App(Message::GatewayUpdated(gateway)),
+            ),
+            cosmic::Task::perform(
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        connection_interface
+                            .and_then(|interface| nm::get_active_connection(&interface))
+                    })
+                    .await
+                    .unwrap_or_default()
+                },
+                |name| cosmic::Action::App(Message::ConnectionNameUpdated(name)),
+            ),
+            cosmic::Task::perform(
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        network_interface
+                            .and_then(|interface| shaping::get_shaping_limit(&interface))
+                    })
+                    .await
+                    .unwrap_or_default()
+                },
+                |limit| cosmic::Action::App(Message::ShapingLimitUpdated(limit)),
+            ),
+            cosmic::Task::perform(
+                async {
+                    tokio::task::spawn_blocking(nm::get_devices)
+                        .await
+                        .unwrap_or_default()
+                },
+                |devices| cosmic::Action::App(Message::DevicesUpdated(devices)),
+            ),
+            cosmic::Task::perform(
+                async {
+                    tokio::task::spawn_blocking(firewall::get_firewall_status)
+                        .await
+                        .unwrap_or_default()
+                },
+                |status| cosmic::Action::App(Message::FirewallStatusUpdated(status)),
+            ),
+            cosmic::Task::perform(
+                async {
+                    tokio::task::spawn_blocking(ports::get_listening_ports)
+                        .await
+                        .unwrap_or_default()
+                },
+                |ports| cosmic::Action::App(Message::ListeningPortsUpdated(ports)),
+            ),
+        ])
+    }
+
     fn format_speed(&self, val: f64) -> String {
         let formatted = if val >= 1000.0 {
             format!("{:.0}", val)
@@ -115,14 +957,13 @@ impl AppModel {
             format!("{:.2}", val)
         };
 
-        // Clean up trailing zeros
-        let result = formatted
-            .trim_end_matches('0')
-            .trim_end_matches('.')
-            .to_string();
-
-        // Final truncation to ensure 5 chars max total
-        result.chars().take(5).collect()
+        // Clean up trailing zeros of the decimal places
+        let formatted = if formatted.contains('.') {
+            formatted.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            formatted.as_str()
+        };
+        locale::localize_number(formatted)
     }
 
     fn get_panel_size(&self) -> u32 {
@@ -198,153 +1039,2044 @@ impl AppModel {
         )
     }
 
-    fn set_download_speed_display(&mut self) {
-        // Closest power of 2
-        let download_power = if self.download_speed > 0 {
-            self.download_speed.ilog2()
+    /// Measures the panel text elements so their widths stay fixed while the values change
+    fn update_text_metrics(&mut self, font_config: &FontConfig) {
+        // Wide enough for the longer of the direction symbols
+        let sample_unit = if self.config.iec_labels {
+            "MiB/s"
+        } else {
+            "Mb/s"
+        };
+        let symbols = [
+            self.config.download_symbol.clone(),
+            self.config.upload_symbol.clone(),
+        ];
+        (self.data_width, self.unit_width) = (0.0, 0.0);
+        for symbol in symbols {
+            let (sample_data, sample_unit) = self.attach_symbol(
+                locale::widest_number(self.get_value_digits()),
+                String::from(sample_unit),
+                &symbol,
+            );
+            let data_width = self.get_text_width_and_height(&sample_data, font_config).0;
+            let unit_width = self.get_text_width_and_height(&sample_unit, font_config).0;
+            self.data_width = self.data_width.max(data_width);
+            self.unit_width = self.unit_width.max(unit_width);
+        }
+        self.line_height = self
+            .get_text_width_and_height("1234567890.kKMGT/Bb↓↑", font_config)
+            .1;
+        self.dot_width = self.get_text_width_and_height("●", font_config).0;
+        self.warning_width = self.get_text_width_and_height("⚠", font_config).0;
+        self.today_width = self.get_text_width_and_height(&fl!("today"), font_config).0;
+        self.interface_font = font_config.clone();
+        self.update_interface_label();
+        self.update_panel_interfaces();
+    }
+
+    fn update_interface_label(&mut self) {
+        let network_interface = if self.config.aggregate_interfaces {
+            Some(fl!("all-interfaces"))
+        } else {
+            self.selected_network_interface_name()
+        };
+        let interface_label = if self.label_flashes > 0 {
+            InterfaceLabel::Name
         } else {
-            0
+            self.config.interface_label
+        };
+        self.interface_label = match (interface_label, network_interface) {
+            (InterfaceLabel::Name, Some(name)) => self.get_interface_display_name(&name),
+            (InterfaceLabel::Abbreviation, Some(name)) => network::abbreviate_interface_name(&name),
+            _ => String::new(),
         };
-        // Dividing by closest power of 1024
-        let download_speed_rebase =
-            self.download_speed as f64 / 2u64.pow(download_power - download_power % 10) as f64;
-        let download_speed_display = if download_power >= 10 {
-            self.format_speed(download_speed_rebase)
+        self.interface_label_width = if self.interface_label.is_empty() {
+            0.0
         } else {
-            // No decimal places if speed <= 1024 bits or Bytes
-            format!("{:.0}", download_speed_rebase)
+            let (label, font) = (self.interface_label.clone(), self.interface_font.clone());
+            self.get_text_width_and_height(&label, &font).0
         };
-        let mut download_unit = String::new();
-        if download_power >= 20 {
-            download_unit.push_str(fl!("mega-short").as_str());
-        } else if download_power >= 10 {
-            download_unit.push_str(fl!("kilo-short").as_str());
+    }
+
+    /// Scale of the prefixes of `unit`, packets are always counted in powers of 1000
+    fn get_unit_scale(&self, unit: Unit) -> UnitScale {
+        match unit {
+            Unit::Packets => UnitScale::Si,
+            _ => self.config.unit_scale,
         }
-        match self.config.unit {
-            Unit::Bits => {
-                download_unit
-                    .push_str(format!("{}/{}", fl!("bits-short"), fl!("second-short")).as_str());
-            }
-            Unit::Bytes => {
-                download_unit
-                    .push_str(format!("{}/{}", fl!("bytes-short"), fl!("second-short")).as_str());
-            }
+    }
+
+    /// Short prefix of the `exponent`th power of `scale`
+    fn get_unit_prefix(&self, exponent: u32, scale: UnitScale) -> String {
+        if scale == UnitScale::Iec && self.config.iec_labels {
+            return match exponent {
+                0 => String::new(),
+                1 => fl!("kibi-short"),
+                2 => fl!("mebi-short"),
+                3 => fl!("gibi-short"),
+                _ => fl!("tebi-short"),
+            };
+        }
+        match exponent {
+            0 => String::new(),
+            1 if scale == UnitScale::Si => fl!("kilo-short-si"),
+            1 => fl!("kilo-short"),
+            2 => fl!("mega-short"),
+            3 => fl!("giga-short"),
+            _ => fl!("tera-short"),
         }
-        download_unit.push_str("  ↓");
-        self.download_speed_display = download_speed_display;
-        self.download_unit = download_unit;
     }
 
-    fn set_upload_speed_display(&mut self) {
-        let upload_power = if self.upload_speed > 0 {
-            // Closest power of 2
-            self.upload_speed.ilog2()
+    /// Spells out a speed for speech, e.g. "12.3 megabits"
+    fn get_spoken_speed(&self, speed: u64, unit: Unit) -> String {
+        let scale = self.get_unit_scale(unit);
+        let exponent = scale.exponent(speed).min(4);
+        let rebase = speed as f64 / scale.base().pow(exponent) as f64;
+        let value = if exponent > 0 {
+            self.format_speed(rebase)
         } else {
-            0
+            locale::localize_number(&format!("{:.0}", rebase))
         };
-        // Dividing by closest power of 1024
-        let upload_speed_rebase =
-            self.upload_speed as f64 / 2u64.pow(upload_power - upload_power % 10) as f64;
-        let upload_speed_display = if upload_power >= 10 {
-            self.format_speed(upload_speed_rebase)
-        } else {
-            // No decimal places if speed <= 1024 bits or Bytes
-            format!("{:.0}", upload_speed_rebase)
+        let prefix = match exponent {
+            4 => fl!("spoken-tera"),
+            3 => fl!("spoken-giga"),
+            2 => fl!("spoken-mega"),
+            1 => fl!("spoken-kilo"),
+            _ => String::new(),
+        };
+        let unit = match unit {
+            Unit::Bits => fl!("spoken-bits"),
+            Unit::Bytes => fl!("spoken-bytes"),
+            Unit::Packets => fl!("spoken-packets"),
+        };
+        format!("{value} {prefix}{unit}")
+    }
+
+    /// Monitored interface and its rates with their units
+    fn get_tooltip(&self) -> String {
+        let rates = |download: (&str, &str), upload: (&str, &str)| {
+            let (first, second) = if self.config.upload_first {
+                (upload, download)
+            } else {
+                (download, upload)
+            };
+            format!("{} {}  {} {}", first.0, first.1, second.0, second.1)
         };
-        let mut upload_unit = String::new();
-        if upload_power >= 20 {
-            upload_unit.push_str(fl!("mega-short").as_str());
-        } else if upload_power >= 10 {
-            upload_unit.push_str(fl!("kilo-short").as_str());
+        let current = rates(
+            (&self.download_speed_display, &self.download_unit),
+            (&self.upload_speed_display, &self.upload_unit),
+        );
+        let tooltip = match self.get_monitored_display_name() {
+            Some(name) => format!("{name}  {current}"),
+            None => current,
+        };
+        if !self.config.show_peak {
+            return tooltip;
+        }
+        let (download, upload) = self.format_peaks();
+        format!(
+            "{tooltip}\n{}  {}",
+            fl!("peak"),
+            rates((&download.0, &download.1), (&upload.0, &upload.1))
+        )
+    }
+
+    /// Most digits of the values shown in the panel
+    fn get_value_digits(&self) -> usize {
+        if self.config.fixed_prefix.is_some() {
+            FIXED_PREFIX_DIGITS
+        } else {
+            VALUE_DIGITS
         }
-        match self.config.unit {
+    }
+
+    /// Formats `amount` of `unit` into a value and its prefix
+    fn format_amount(&self, amount: u64, unit: Unit) -> (String, String) {
+        let scale = self.get_unit_scale(unit);
+        // Closest power of the scale, unless the prefix is fixed
+        let exponent = match self.config.fixed_prefix {
+            Some(prefix) => {
+                let limit = 10u64.pow(FIXED_PREFIX_DIGITS as u32);
+                (prefix.exponent()..4)
+                    .find(|exponent| amount / scale.base().pow(*exponent) < limit)
+                    .unwrap_or(4)
+            }
+            None => scale.exponent(amount).min(4),
+        };
+        let rebase = amount as f64 / scale.base().pow(exponent) as f64;
+        let display = if exponent > 0 {
+            self.format_speed(rebase)
+        } else {
+            // No decimal places below a kilo
+            locale::localize_number(&format!("{:.0}", rebase))
+        };
+        let display = if self.config.tabular_digits {
+            locale::pad_number(&display, self.get_value_digits())
+        } else {
+            display
+        };
+        (display, self.get_unit_prefix(exponent, scale))
+    }
+
+    /// Formats `speed` into a value and a unit ending with `arrow`
+    fn format_rate(&self, speed: u64, unit: Unit, arrow: &str) -> (String, String) {
+        let (display, mut rate_unit) = self.format_amount(speed, unit);
+        match unit {
             Unit::Bits => {
-                upload_unit
+                rate_unit
                     .push_str(format!("{}/{}", fl!("bits-short"), fl!("second-short")).as_str());
             }
             Unit::Bytes => {
-                upload_unit
+                rate_unit
                     .push_str(format!("{}/{}", fl!("bytes-short"), fl!("second-short")).as_str());
             }
+            Unit::Packets => {
+                rate_unit.push_str(fl!("packets-per-second-short").as_str());
+            }
         }
-        upload_unit.push_str("  ↑");
-        self.upload_speed_display = upload_speed_display;
-        self.upload_unit = upload_unit;
+        self.attach_symbol(display, rate_unit, arrow)
     }
 
-    fn horizontal_layout(&self) -> Element<'_, Message> {
-        let theme = cosmic::theme::active();
-        let cosmic = theme.cosmic();
-        let mut elements: Vec<Element<Message>> = Vec::new();
-        let mut widget_width = 0.0;
-        let row_width = self.data_width + cosmic.space_none() as f32 + self.unit_width;
+    /// Formats a total `amount` into a value and a unit ending with `arrow`
+    fn format_total(&self, amount: u64, unit: Unit, arrow: &str) -> (String, String) {
+        let (display, mut total_unit) = self.format_amount(amount, unit);
+        total_unit.push_str(
+            match unit {
+                Unit::Bits => fl!("bits-short"),
+                Unit::Bytes => fl!("bytes-short"),
+                Unit::Packets => fl!("packets-short"),
+            }
+            .as_str(),
+        );
+        self.attach_symbol(display, total_unit, arrow)
+    }
 
-        if self.config.show_download_speed {
-            elements.push(
-                container(
-                    row!(
-                        container(self.core.applet.text(&self.download_speed_display))
-                            .align_left(self.data_width),
-                        container(self.core.applet.text(&self.download_unit))
-                            .align_right(self.unit_width),
-                    )
-                    .spacing(cosmic.space_none())
-                    .clip(true),
-                )
-                .width(row_width)
-                .height(self.line_height)
-                .into(),
-            );
-            widget_width += row_width;
+    /// Puts the direction `arrow` before the value or after the unit
+    fn attach_symbol(&self, display: String, mut unit: String, arrow: &str) -> (String, String) {
+        if arrow.is_empty() {
+            (display, unit)
+        } else if self.config.symbol_before_value {
+            (format!("{arrow} {display}"), unit)
+        } else {
+            unit.push_str("  ");
+            unit.push_str(arrow);
+            (display, unit)
         }
-        if self.config.show_upload_speed {
-            if self.config.show_download_speed {
-                widget_width += cosmic.space_xs() as f32;
+    }
+
+    /// Formats an amount of bytes with the closest power of the configured scale
+    fn format_size(&self, bytes: u64) -> String {
+        let scale = self.config.unit_scale;
+        let exponent = scale.exponent(bytes).min(4);
+        let value = bytes as f64 / scale.base().pow(exponent) as f64;
+        format!(
+            "{} {}{}",
+            self.format_speed(value),
+            self.get_unit_prefix(exponent, scale),
+            fl!("bytes-short")
+        )
+    }
+
+    /// Formats traffic in `unit` as a rate or a total depending on the
+    /// display mode, from speeds and totals as bytes and packets
+    fn format_traffic(
+        &self,
+        unit: Unit,
+        speed: (u64, u64),
+        total: (u64, u64),
+        arrow: &str,
+    ) -> (String, String) {
+        match self.config.display_mode {
+            DisplayMode::Rate if speed.0 < self.config.minimum_rate as u64 => {
+                if self.config.hide_below_minimum {
+                    (String::new(), String::new())
+                } else {
+                    self.format_rate(0, unit, arrow)
+                }
+            }
+            DisplayMode::Rate => self.format_rate(unit.convert(speed.0, speed.1), unit, arrow),
+            DisplayMode::Total => self.format_total(unit.convert(total.0, total.1), unit, arrow),
+            // Daily usage is only accounted in bytes
+            DisplayMode::Today => {
+                let unit = match unit {
+                    Unit::Packets => Unit::Bytes,
+                    unit => unit,
+                };
+                self.format_total(unit.convert(total.0, total.1), unit, arrow)
             }
-            elements.push(
-                container(
-                    row!(
-                        container(self.core.applet.text(&self.upload_speed_display))
-                            .align_left(self.data_width),
-                        container(self.core.applet.text(&self.upload_unit))
-                            .align_right(self.unit_width),
-                    )
-                    .spacing(cosmic.space_none())
-                    .clip(true),
-                )
-                .width(row_width)
-                .height(self.line_height)
-                .into(),
-            );
-            widget_width += row_width;
         }
+    }
 
-        let padding = self.core.applet.suggested_padding(true);
-        widget_width += 2.0 * padding.0 as f32;
-        container(
-            Row::from_vec(elements)
-                .spacing(cosmic.space_xs())
-                .clip(true),
+    /// Formats traffic in `unit` and, when both are shown, in its counterpart
+    fn format_traffic_with_counterpart(
+        &self,
+        unit: Unit,
+        speed: (u64, u64),
+        total: (u64, u64),
+        arrow: &str,
+    ) -> ((String, String), Option<(String, String)>) {
+        (
+            self.format_traffic(unit, speed, total, arrow),
+            self.config
+                .show_both_units
+                .then(|| self.format_traffic(unit.counterpart(), speed, total, arrow)),
         )
-        .align_y(Alignment::Center)
-        .padding([padding.1, padding.0])
-        .height(self.line_height + 2.0 * padding.1 as f32)
-        .width(widget_width)
-        .into()
     }
-}
 
-impl cosmic::Application for AppModel {
-    type Executor = cosmic::executor::Default;
-
-    type Flags = ();
+    /// Download and upload totals, as bytes and packets, counted since the
+    /// start of the display mode
+    fn get_totals(
+        &self,
+        session_totals: &SessionTotals,
+        usage: &UsageAccounting,
+    ) -> ((u64, u64), (u64, u64)) {
+        match self.config.display_mode {
+            DisplayMode::Today => {
+                let today = usage.today();
+                ((today.received_bytes, 0), (today.sent_bytes, 0))
+            }
+            _ => (
+                (
+                    session_totals.received_bytes,
+                    session_totals.received_packets,
+                ),
+                (session_totals.sent_bytes, session_totals.sent_packets),
+            ),
+        }
+    }
 
-    type Message = Message;
+    fn set_download_speed_display(&mut self) {
+        let unit = self
+            .config
+            .get_download_unit_for(self.get_monitored_interface().as_deref());
+        let display;
+        (display, self.download_counterpart) = self.format_traffic_with_counterpart(
+            unit,
+            (
+                self.monitored.download_speed,
+                self.monitored.download_packet_speed,
+            ),
+            self.get_totals(&self.monitored.session_totals, &self.monitored.usage)
+                .0,
+            &self.config.download_symbol,
+        );
+        (self.download_speed_display, self.download_unit) = display;
+    }
 
-    const APP_ID: &'static str = "io.github.avomar.cosmic-ext-applet-bitrate";
+    fn set_upload_speed_display(&mut self) {
+        let unit = self
+            .config
+            .get_upload_unit_for(self.get_monitored_interface().as_deref());
+        let display;
+        (display, self.upload_counterpart) = self.format_traffic_with_counterpart(
+            unit,
+            (
+                self.monitored.upload_speed,
+                self.monitored.upload_packet_speed,
+            ),
+            self.get_totals(&self.monitored.session_totals, &self.monitored.usage)
+                .1,
+            &self.config.upload_symbol,
+        );
+        (self.upload_speed_display, self.upload_unit) = display;
+    }
 
-    fn core(&self) -> &cosmic::Core {
-        &self.core
+    /// Rebuilds the side by side interfaces from the config, keeping the
+    /// counters of interfaces that stay
+    fn update_panel_interfaces(&mut self) {
+        let mut previous = std::mem::take(&mut self.panel_interfaces);
+        for name in self.config.panel_interfaces.clone() {
+            let label = match self.config.interface_label {
+                InterfaceLabel::Abbreviation => network::abbreviate_interface_name(&name),
+                _ => self.get_interface_display_name(&name),
+            };
+            let font = self.interface_font.clone();
+            let label_width = self.get_text_width_and_height(&label, &font).0;
+            let panel_interface = match previous.iter().position(|entry| entry.name == name) {
+                Some(index) => PanelInterface {
+                    label,
+                    label_width,
+                    ..previous.swap_remove(index)
+                },
+                None => PanelInterface {
+                    counters: InterfaceCounters::new(self.stats.as_ref(), &name),
+                    name,
+                    label,
+                    label_width,
+                    download_speed_display: String::new(),
+                    download_unit: String::new(),
+                    download_counterpart: None,
+                    upload_speed_display: String::new(),
+                    upload_unit: String::new(),
+                    upload_counterpart: None,
+                },
+            };
+            self.panel_interfaces.push(panel_interface);
+        }
+        self.set_panel_interfaces_display();
+    }
+
+    /// Reads the counters of the side by side interfaces and of every
+    /// interface listed in the popup
+    fn update_interface_counters(&mut self) {
+        let smoothing = self.config.smoothing as usize;
+        for panel_interface in &mut self.panel_interfaces {
+            panel_interface
+                .counters
+                .update(self.stats.as_ref(), &panel_interface.name, smoothing);
+        }
+        for (network_interface, counters) in &mut self.interface_counters {
+            let (received_bytes, sent_bytes) =
+                counters.update(self.stats.as_ref(), network_interface, smoothing);
+            self.interface_usage
+                .entry(network_interface.clone())
+                .or_default()
+                .record(received_bytes, sent_bytes);
+            if let Some(usage_database) = &mut self.usage_database {
+                usage_database.record_interface(network_interface, received_bytes, sent_bytes);
+            }
+        }
+        self.set_panel_interfaces_display();
+    }
+
+    /// Follows the active interfaces, keeping the counters of those that stay
+    /// unless another interface took their name
+    fn update_interface_counters_list(&mut self) {
+        let mut previous = std::mem::take(&mut self.interface_counters);
+        for network_interface in &self.network_interfaces {
+            let counters = match previous
+                .iter()
+                .position(|(name, _)| name == network_interface)
+            {
+                Some(index) => {
+                    let mut counters = previous.swap_remove(index).1;
+                    counters.check_ifindex(self.stats.as_ref(), network_interface);
+                    counters
+                }
+                None => InterfaceCounters::new(self.stats.as_ref(), network_interface),
+            };
+            self.interface_counters
+                .push((network_interface.clone(), counters));
+        }
+        for panel_interface in &mut self.panel_interfaces {
+            panel_interface
+                .counters
+                .check_ifindex(self.stats.as_ref(), &panel_interface.name);
+        }
+    }
+
+    /// Formats speeds measured in bytes per second in the units configured
+    /// for `network_interface`
+    fn format_rates(
+        &self,
+        network_interface: &str,
+        counters: &InterfaceCounters,
+    ) -> ((String, String), (String, String)) {
+        let download_unit = self.config.get_download_unit_for(Some(network_interface));
+        let upload_unit = self.config.get_upload_unit_for(Some(network_interface));
+        (
+            self.format_rate(
+                download_unit.convert(counters.download_speed, counters.download_packet_speed),
+                download_unit,
+                &self.config.download_symbol,
+            ),
+            self.format_rate(
+                upload_unit.convert(counters.upload_speed, counters.upload_packet_speed),
+                upload_unit,
+                &self.config.upload_symbol,
+            ),
+        )
+    }
+
+    fn set_panel_interfaces_display(&mut self) {
+        let displays: Vec<_> = self
+            .panel_interfaces
+            .iter()
+            .map(|panel_interface| {
+                let name = Some(panel_interface.name.as_str());
+                let counters = &panel_interface.counters;
+                let (download_total, upload_total) =
+                    self.get_totals(&counters.session_totals, &counters.usage);
+                (
+                    self.format_traffic_with_counterpart(
+                        self.config.get_download_unit_for(name),
+                        (counters.download_speed, counters.download_packet_speed),
+                        download_total,
+                        &self.config.download_symbol,
+                    ),
+                    self.format_traffic_with_counterpart(
+                        self.config.get_upload_unit_for(name),
+                        (counters.upload_speed, counters.upload_packet_speed),
+                        upload_total,
+                        &self.config.upload_symbol,
+                    ),
+                )
+            })
+            .collect();
+        for (panel_interface, (download, upload)) in self.panel_interfaces.iter_mut().zip(displays)
+        {
+            (
+                (
+                    panel_interface.download_speed_display,
+                    panel_interface.download_unit,
+                ),
+                panel_interface.download_counterpart,
+            ) = download;
+            (
+                (
+                    panel_interface.upload_speed_display,
+                    panel_interface.upload_unit,
+                ),
+                panel_interface.upload_counterpart,
+            ) = upload;
+        }
+    }
+
+    /// Rebuilds the popup tabs from the active interfaces, staying on the
+    /// active tab while it exists
+    fn update_popup_tabs(&mut self) {
+        let active = self
+            .popup_tabs
+            .active_data::<PopupTab>()
+            .cloned()
+            .or_else(|| {
+                self.selected_network_interface_name()
+                    .map(PopupTab::Interface)
+            })
+            .unwrap_or(PopupTab::Overview);
+        let mut popup_tabs = segmented_button::SingleSelectModel::default();
+        for network_interface in &self.network_interfaces {
+            popup_tabs
+                .insert()
+                .text(self.get_interface_display_name(network_interface))
+                .data(PopupTab::Interface(network_interface.clone()));
+        }
+        popup_tabs
+            .insert()
+            .text(fl!("overview"))
+            .data(PopupTab::Overview);
+        popup_tabs.insert().text(fl!("usage")).data(PopupTab::Usage);
+        popup_tabs
+            .insert()
+            .text(fl!("settings"))
+            .data(PopupTab::Settings);
+        self.popup_tabs = popup_tabs;
+        self.activate_popup_tab(&active);
+    }
+
+    /// Activates the tab showing `tab`, or the overview if there is none
+    fn activate_popup_tab(&mut self, tab: &PopupTab) {
+        let entity = self
+            .popup_tabs
+            .iter()
+            .find(|&entity| self.popup_tabs.data::<PopupTab>(entity) == Some(tab))
+            .or_else(|| {
+                self.popup_tabs.iter().find(|&entity| {
+                    self.popup_tabs.data::<PopupTab>(entity) == Some(&PopupTab::Overview)
+                })
+            });
+        if let Some(entity) = entity {
+            self.popup_tabs.activate(entity);
+        }
+    }
+
+    /// A speed value and its unit, aligned to fixed widths
+    fn rate_element<'a>(
+        &'a self,
+        speed_display: &'a str,
+        unit: &'a str,
+        color: Option<iced::Color>,
+    ) -> Element<'a, Message> {
+        let theme = cosmic::theme::active();
+        let space_none = theme.cosmic().space_none();
+        let text = |content: &'a str| {
+            let text = self.core.applet.text(content);
+            match color {
+                Some(color) => text.class(cosmic::theme::Text::Color(color)),
+                None => text,
+            }
+        };
+        if !self.config.show_unit {
+            return container(text(speed_display))
+                .align_left(self.data_width)
+                .height(self.line_height)
+                .into();
+        }
+        container(
+            row!(
+                container(text(speed_display)).align_left(self.data_width),
+                container(text(unit)).align_right(self.unit_width),
+            )
+            .spacing(space_none)
+            .clip(true),
+        )
+        .width(self.data_width + space_none as f32 + self.unit_width)
+        .height(self.line_height)
+        .into()
+    }
+
+    fn horizontal_layout<'a>(&'a self) -> Element<'a, Message> {
+        let theme = cosmic::theme::active();
+        let cosmic = theme.cosmic();
+        let mut elements: Vec<Element<Message>> = Vec::new();
+        let mut widget_width = 0.0;
+        let row_width = if self.config.show_unit {
+            self.data_width + cosmic.space_none() as f32 + self.unit_width
+        } else {
+            self.data_width
+        };
+        let colors = self.config.palette.colors(cosmic);
+
+        if let (true, Some(online)) = (self.config.show_reachability, self.online) {
+            let color = if online { colors.good } else { colors.bad };
+            elements.push(
+                container(
+                    self.core
+                        .applet
+                        .text("●")
+                        .class(cosmic::theme::Text::Color(color)),
+                )
+                .width(self.dot_width)
+                .height(self.line_height)
+                .into(),
+            );
+            widget_width += self.dot_width;
+        }
+
+        if self.link_local_only {
+            if !elements.is_empty() {
+                widget_width += cosmic.space_xs() as f32;
+            }
+            elements.push(
+                container(
+                    self.core
+                        .applet
+                        .text("⚠")
+                        .class(cosmic::theme::Text::Color(colors.warning)),
+                )
+                .width(self.warning_width)
+                .height(self.line_height)
+                .into(),
+            );
+            widget_width += self.warning_width;
+        }
+
+        if self.config.display_mode == DisplayMode::Today {
+            if !elements.is_empty() {
+                widget_width += cosmic.space_xs() as f32;
+            }
+            elements.push(
+                container(self.core.applet.text(fl!("today")))
+                    .width(self.today_width)
+                    .height(self.line_height)
+                    .into(),
+            );
+            widget_width += self.today_width;
+        }
+
+        // Past the data cap thresholds the rates take the warning colors
+        let data_cap_color = self.get_crossed_data_cap_threshold().map(|threshold| {
+            if threshold >= 100 {
+                colors.bad
+            } else {
+                colors.warning
+            }
+        });
+
+        // A value followed by the one in the counterpart unit, if shown
+        let values =
+            |display: &'a str, unit: &'a str, counterpart: &'a Option<(String, String)>| {
+                let mut values = vec![(display, unit)];
+                if let Some((display, unit)) = counterpart {
+                    values.push((display.as_str(), unit.as_str()));
+                }
+                values
+            };
+
+        // Whether the upload outweighs the download in the display mode
+        let upload_busier =
+            |speed: (u64, u64), totals: ((u64, u64), (u64, u64))| match self.config.display_mode {
+                DisplayMode::Rate => speed.1 > speed.0,
+                _ => totals.1.0 > totals.0.0,
+            };
+
+        // Each shown interface is a label followed by its rates
+        let mut groups = Vec::new();
+        if self.panel_interfaces.is_empty() {
+            groups.push((
+                self.get_monitored_interface(),
+                self.interface_label.as_str(),
+                self.interface_label_width,
+                upload_busier(
+                    (self.monitored.download_speed, self.monitored.upload_speed),
+                    self.get_totals(&self.monitored.session_totals, &self.monitored.usage),
+                ),
+                values(
+                    &self.download_speed_display,
+                    &self.download_unit,
+                    &self.download_counterpart,
+                ),
+                values(
+                    &self.upload_speed_display,
+                    &self.upload_unit,
+                    &self.upload_counterpart,
+                ),
+            ));
+        } else {
+            for panel_interface in &self.panel_interfaces {
+                let counters = &panel_interface.counters;
+                groups.push((
+                    Some(panel_interface.name.clone()),
+                    panel_interface.label.as_str(),
+                    panel_interface.label_width,
+                    upload_busier(
+                        (counters.download_speed, counters.upload_speed),
+                        self.get_totals(&counters.session_totals, &counters.usage),
+                    ),
+                    values(
+                        &panel_interface.download_speed_display,
+                        &panel_interface.download_unit,
+                        &panel_interface.download_counterpart,
+                    ),
+                    values(
+                        &panel_interface.upload_speed_display,
+                        &panel_interface.upload_unit,
+                        &panel_interface.upload_counterpart,
+                    ),
+                ));
+            }
+        }
+
+        for (network_interface, label, label_width, upload_busier, download, upload) in groups {
+            let network_interface = network_interface.as_deref();
+            let custom_color = self
+                .config
+                .get_color_for(network_interface)
+                .and_then(palette::parse_color);
+            // Summed rates belong to no interface and stay uncolored
+            let interface_color = network_interface
+                .map(|name| custom_color.unwrap_or_else(|| palette::interface_color(name)));
+            let color = data_cap_color.or(match self.config.interface_coloring {
+                InterfaceColoring::Text => interface_color,
+                _ => custom_color,
+            });
+            if let (InterfaceColoring::Dot, Some(interface_color)) =
+                (self.config.interface_coloring, interface_color)
+            {
+                if !elements.is_empty() {
+                    widget_width += cosmic.space_xs() as f32;
+                }
+                elements.push(
+                    container(
+                        self.core
+                            .applet
+                            .text("●")
+                            .class(cosmic::theme::Text::Color(interface_color)),
+                    )
+                    .width(self.dot_width)
+                    .height(self.line_height)
+                    .into(),
+                );
+                widget_width += self.dot_width;
+            }
+            if !label.is_empty() {
+                if !elements.is_empty() {
+                    widget_width += cosmic.space_xs() as f32;
+                }
+                let text = self.core.applet.text(label);
+                let text = match color {
+                    Some(color) => text.class(cosmic::theme::Text::Color(color)),
+                    None => text,
+                };
+                elements.push(
+                    container(text)
+                        .width(label_width)
+                        .height(self.line_height)
+                        .into(),
+                );
+                widget_width += label_width;
+            }
+
+            let mut show_download = self.config.shows_download_speed_for(network_interface);
+            let mut show_upload = self.config.shows_upload_speed_for(network_interface);
+            if self.config.compact && show_download && show_upload {
+                show_download = !upload_busier;
+                show_upload = upload_busier;
+            }
+            let mut rates = Vec::new();
+            if show_download {
+                rates.extend(download);
+            }
+            if show_upload {
+                let position = if self.config.upload_first {
+                    0
+                } else {
+                    rates.len()
+                };
+                rates.splice(position..position, upload);
+            }
+            for (speed_display, unit) in rates {
+                if !elements.is_empty() {
+                    widget_width += cosmic.space_xs() as f32;
+                }
+                elements.push(self.rate_element(speed_display, unit, color));
+                widget_width += row_width;
+            }
+        }
+
+        if self.config.show_sparkline {
+            let length = self.config.sparkline_length as usize;
+            let samples: Vec<_> = self.history.latest(length).copied().collect();
+            let width = self.line_height * SPARKLINE_ASPECT_RATIO;
+            if !elements.is_empty() {
+                widget_width += cosmic.space_xs() as f32;
+            }
+            elements.push(
+                widget::svg(graph::sparkline(&samples, width, self.line_height, &colors))
+                    .width(width)
+                    .height(self.line_height)
+                    .into(),
+            );
+            widget_width += width;
+        }
+
+        let padding = self.core.applet.suggested_padding(true);
+        widget_width += 2.0 * padding.0 as f32;
+        container(
+            Row::from_vec(elements)
+                .spacing(cosmic.space_xs())
+                .clip(true),
+        )
+        .align_y(Alignment::Center)
+        .padding([padding.1, padding.0])
+        .height(self.line_height + 2.0 * padding.1 as f32)
+        .width(widget_width)
+        .into()
+    }
+
+    /// Highest of the data cap percentages the monthly usage crossed
+    fn get_crossed_data_cap_threshold(&self) -> Option<u8> {
+        let data_cap = self.config.get_data_cap()?;
+        let this_month = self.monitored.usage.this_cycle(self.config.billing_day);
+        let percent = (this_month.received_bytes + this_month.sent_bytes) * 100 / data_cap;
+        self.config
+            .data_cap_thresholds
+            .iter()
+            .copied()
+            .filter(|threshold| *threshold as u64 <= percent)
+            .max()
+    }
+
+    /// Notifies once per monthly cycle about each data cap percentage crossed
+    fn check_data_cap(&mut self) {
+        let Some(threshold) = self.get_crossed_data_cap_threshold() else {
+            return;
+        };
+        let Some(cycle) =
+            usage::cycle_start(chrono::Local::now().date_naive(), self.config.billing_day)
+        else {
+            return;
+        };
+        if self
+            .data_cap_notified
+            .is_some_and(|notified| notified.0 == cycle && notified.1 >= threshold)
+        {
+            return;
+        }
+        self.data_cap_notified = Some((cycle, threshold));
+        let Some(data_cap) = self.config.get_data_cap() else {
+            return;
+        };
+        let this_month = self.monitored.usage.this_cycle(self.config.billing_day);
+        let used = this_month.received_bytes + this_month.sent_bytes;
+        notification::notify(
+            fl!("data-cap-crossed", percent = threshold),
+            fl!(
+                "data-cap-remaining",
+                remaining = self.format_size(data_cap.saturating_sub(used)),
+                cap = self.format_size(data_cap)
+            ),
+        );
+        self.emit(Event::DataCapCrossed {
+            percent: threshold,
+            used,
+            data_cap,
+        });
+    }
+
+    /// Adds the usage a vnStat database recorded before this applet, and
+    /// reloads the usage with it
+    fn import_vnstat(&mut self, path: &Path) {
+        let Some(usage_database) = &mut self.usage_database else {
+            return;
+        };
+        let records = match vnstat::read(path) {
+            Ok(records) => records,
+            Err(why) => {
+                eprintln!("error while reading vnStat database: {why}");
+                return;
+            }
+        };
+        // The reloaded usage comes from the database only
+        let mut usage = UsageAccounting::default();
+        let mut interface_usage = BTreeMap::new();
+        let result = usage_database.flush().and_then(|()| {
+            let count = usage_database.import(&records)?;
+            usage_database.load(&mut usage)?;
+            usage_database.load_interfaces(&mut interface_usage)?;
+            Ok(count)
+        });
+        match result {
+            Ok(count) => {
+                self.monitored.usage = usage;
+                self.interface_usage = interface_usage;
+                notification::notify(fl!("import-vnstat"), fl!("vnstat-imported", count = count));
+            }
+            Err(why) => eprintln!("error while importing vnStat database: {why}"),
+        }
+    }
+
+    fn read_heatmap(&mut self) {
+        let Some(usage_database) = &self.usage_database else {
+            return;
+        };
+        match usage_database.heatmap() {
+            Ok(heatmap) => self.heatmap = Some(heatmap),
+            Err(why) => eprintln!("error while reading usage database: {why}"),
+        }
+    }
+
+    fn save_state(&mut self) {
+        if let Err(why) = persistence::save(Self::APP_ID, &self.history) {
+            eprintln!("error while saving state: {why}");
+        }
+        if let Some(usage_database) = &mut self.usage_database
+            && let Err(why) = usage_database.flush().and_then(|()| {
+                usage_database.compact(
+                    self.config.hourly_usage_retention,
+                    self.config.usage_retention,
+                )
+            })
+        {
+            eprintln!("error while writing usage database: {why}");
+        }
+    }
+
+    /// Received and sent bytes of `usage` with their symbols
+    fn format_usage(&self, usage: DailyUsage) -> String {
+        format!(
+            "{} {}  {} {}",
+            self.config.download_symbol,
+            self.format_size(usage.received_bytes),
+            self.config.upload_symbol,
+            self.format_size(usage.sent_bytes)
+        )
+    }
+
+    /// Traffic of today, yesterday and the current month, how it compares
+    /// to a week and a month before, and how much of the data cap is left
+    fn view_daily_usage(&self) -> Element<'_, Message> {
+        let usage = |usage: DailyUsage| widget::text::body(self.format_usage(usage));
+        let this_month = self.monitored.usage.this_cycle(self.config.billing_day);
+        let mut content = column!(
+            widget::settings::item(fl!("today"), usage(self.monitored.usage.today())),
+            widget::settings::item(fl!("yesterday"), usage(self.monitored.usage.yesterday())),
+            widget::settings::item(fl!("this-month"), usage(this_month)),
+        )
+        .spacing(theme::active().cosmic().space_xxxs());
+        for (label, current, previous) in [
+            (
+                fl!("today-vs-last-week"),
+                self.monitored.usage.today(),
+                self.monitored.usage.last_week(),
+            ),
+            (
+                fl!("this-month-vs-last"),
+                this_month,
+                self.monitored
+                    .usage
+                    .previous_cycle_to_date(self.config.billing_day),
+            ),
+        ] {
+            let current = current.received_bytes + current.sent_bytes;
+            let previous = previous.received_bytes + previous.sent_bytes;
+            if previous == 0 {
+                continue;
+            }
+            let change = (current as f64 / previous as f64 - 1.0) * 100.0;
+            content = content.push(widget::settings::item(
+                label,
+                widget::text::body(fl!(
+                    "usage-change",
+                    change = format!("{change:+.0}"),
+                    previous = self.format_size(previous)
+                )),
+            ));
+        }
+        if let Some(data_cap) = self.config.get_data_cap() {
+            let used = this_month.received_bytes + this_month.sent_bytes;
+            content = content
+                .push(widget::settings::item(
+                    fl!("data-cap"),
+                    widget::text::body(fl!(
+                        "data-cap-remaining",
+                        remaining = self.format_size(data_cap.saturating_sub(used)),
+                        cap = self.format_size(data_cap)
+                    )),
+                ))
+                .push(widget::progress_bar(
+                    0.0..=1.0,
+                    (used as f64 / data_cap as f64).min(1.0) as f32,
+                ));
+        }
+        content.into()
+    }
+
+    /// Unit the history is shown in, bytes instead of the packets it lacks
+    fn get_history_unit(&self) -> Unit {
+        match self
+            .config
+            .get_download_unit_for(self.get_monitored_interface().as_deref())
+        {
+            Unit::Packets => Unit::Bytes,
+            unit => unit,
+        }
+    }
+
+    /// Lowest, highest and mean speeds kept in the history
+    fn view_statistics(&self) -> Option<Element<'_, Message>> {
+        let (received, sent) = self.history.statistics()?;
+        let unit = self.get_history_unit();
+        let rates = |download: u64, upload: u64| {
+            let download = self.format_rate(
+                unit.convert(download, 0),
+                unit,
+                &self.config.download_symbol,
+            );
+            let upload =
+                self.format_rate(unit.convert(upload, 0), unit, &self.config.upload_symbol);
+            widget::text::body(format!(
+                "{} {}  {} {}",
+                download.0, download.1, upload.0, upload.1
+            ))
+        };
+        Some(
+            column!(
+                widget::settings::item(fl!("minimum"), rates(received.min, sent.min)),
+                widget::settings::item(fl!("maximum"), rates(received.max, sent.max)),
+                widget::settings::item(fl!("average"), rates(received.mean, sent.mean)),
+            )
+            .spacing(theme::active().cosmic().space_xxxs())
+            .into(),
+        )
+    }
+
+    /// Chart of the popup graph, with its scale in the history unit
+    fn get_graph_chart(&self) -> (graph::Chart, u64) {
+        let samples: Vec<_> = self.history.samples().copied().collect();
+        let (download_peak, upload_peak) = self.session_peaks;
+        let chart = graph::Chart::new(
+            &samples,
+            chrono::TimeDelta::seconds(self.graph_range as i64),
+            iced::Size::new(GRAPH_WIDTH, GRAPH_HEIGHT),
+        )
+        .log_scale(self.config.graph_log_scale)
+        .style(self.config.graph_style)
+        .events(self.interface_changes.iter().map(|(time, _)| *time))
+        .peaks(
+            download_peak.map(|peak| peak.time),
+            upload_peak.map(|peak| peak.time),
+        );
+        let max = chart
+            .samples()
+            .iter()
+            .map(|sample| match self.config.graph_style {
+                GraphStyle::Lines => sample.received.max(sample.sent),
+                GraphStyle::Stacked => sample.received + sample.sent,
+            })
+            .max()
+            .unwrap_or(0);
+        let unit = self.get_history_unit();
+        (chart, graph::nice_ceiling(unit.convert(max, 0).max(1)))
+    }
+
+    /// Graph of the monitored speeds over the selected range, zoomed with the
+    /// wheel, scaled to the highest of them and marking the session peaks and
+    /// interface changes, with the details of the hovered update
+    fn view_graph(&self) -> Element<'_, Message> {
+        let theme = theme::active();
+        let cosmic = theme.cosmic();
+        let unit = self.get_history_unit();
+        let (download_peak, upload_peak) = self.session_peaks;
+        let (chart, scale) = self.get_graph_chart();
+        let (value, rate_unit) = self.format_rate(scale, unit, "");
+        let hovered = self.graph_hover.and_then(|x| chart.sample_at(x));
+        let image = chart.draw(
+            scale as f32 / unit.convert(1, 0) as f32,
+            hovered,
+            &self.config.palette.colors(cosmic),
+            cosmic.on_bg_color().into(),
+        );
+        let graph = row!(
+            iced::widget::mouse_area(widget::svg(image).width(GRAPH_WIDTH).height(GRAPH_HEIGHT))
+                .on_move(|position| Message::GraphHovered(Some(position.x)))
+                .on_exit(Message::GraphHovered(None))
+                .on_scroll(Message::GraphZoomed),
+            column!(
+                widget::text::caption(format!("{value} {rate_unit}")),
+                container(widget::text::caption("0")).align_bottom(iced::Length::Fill),
+            )
+            .height(GRAPH_HEIGHT),
+        )
+        .spacing(cosmic.space_xxs());
+
+        let mut ranges = row!().spacing(cosmic.space_xxxs());
+        for range in GRAPH_RANGES {
+            ranges = ranges.push(
+                button::text(format_duration(range))
+                    .selected(self.graph_range == range)
+                    .on_press(Message::GraphRangeChanged(range)),
+            );
+        }
+        ranges = ranges.push(button::text(fl!("save-graph")).on_press(Message::SaveGraph));
+        let mut content = column!(graph, ranges).spacing(cosmic.space_xxxs());
+
+        let Some((index, sample)) = hovered.map(|index| (index, chart.samples()[index])) else {
+            return content.into();
+        };
+        let download = self.format_rate(
+            unit.convert(sample.received, 0),
+            unit,
+            &self.config.download_symbol,
+        );
+        let upload = self.format_rate(
+            unit.convert(sample.sent, 0),
+            unit,
+            &self.config.upload_symbol,
+        );
+        content = content.push(widget::text::caption(format!(
+            "{}  {} {}  {} {}",
+            sample.time.format("%H:%M:%S"),
+            download.0,
+            download.1,
+            upload.0,
+            upload.1
+        )));
+        for (peak, label) in [
+            (download_peak, fl!("session-peak-download")),
+            (upload_peak, fl!("session-peak-upload")),
+        ] {
+            if peak.and_then(|peak| chart.sample_index(peak.time)) == Some(index) {
+                content = content.push(widget::text::caption(label));
+            }
+        }
+        for (time, network_interface) in &self.interface_changes {
+            if chart.event_index(*time) == Some(index) {
+                let interface = match network_interface {
+                    Some(network_interface) => self.get_interface_display_name(network_interface),
+                    None => fl!("none"),
+                };
+                content = content.push(widget::text::caption(fl!(
+                    "interface-changed",
+                    interface = interface
+                )));
+            }
+        }
+        content.into()
+    }
+
+    /// Popup page of `network_interface`, with the details of the monitored
+    /// interface on its own page
+    fn view_interface_page<'a>(&'a self, network_interface: &'a str) -> Element<'a, Message> {
+        let Spacing {
+            space_xxxs,
+            space_xxs,
+            space_s,
+            ..
+        } = theme::active().cosmic().spacing;
+        let monitored =
+            self.selected_network_interface_name().as_deref() == Some(network_interface);
+
+        let mut content = if monitored {
+            let header = match &self.connection_name {
+                Some(connection_name) => connection_name.clone(),
+                None => self.get_interface_display_name(network_interface),
+            };
+            let mut header = column!(
+                widget::settings::item::builder(header)
+                    .description(network_interface)
+                    .control(
+                        button::standard(fl!("reconnect"))
+                            .on_press_maybe((!self.reconnecting).then_some(Message::Reconnect))
+                    )
+            )
+            .spacing(space_xxxs);
+            if self.link_local_only {
+                let colors = self.config.palette.colors(theme::active().cosmic());
+                header = header.push(
+                    widget::text::body(fl!("link-local-only"))
+                        .class(cosmic::theme::Text::Color(colors.warning)),
+                );
+            }
+            let mut content = column!(
+                padded_control(header),
+                padded_control(
+                    widget::settings::item::builder(fl!("current-rates"))
+                        .description(fl!("announce-shortcut"))
+                        .control(button::standard(fl!("announce")).on_press(Message::Announce))
+                ),
+                padded_control(widget::settings::item(fl!("peak"), {
+                    let (download, upload) = self.format_peaks();
+                    widget::text::body(format!(
+                        "{} {}  {} {}",
+                        download.0, download.1, upload.0, upload.1
+                    ))
+                })),
+                padded_control(self.view_graph()),
+            );
+            if let Some(statistics) = self.view_statistics() {
+                content = content.push(padded_control(statistics));
+            }
+            content
+                .push(padded_control(self.view_daily_usage()))
+                .push(padded_control(widget::settings::item(
+                    fl!("history"),
+                    button::standard(fl!("export")).on_press(Message::Export),
+                )))
+        } else {
+            let index = self
+                .network_interfaces
+                .iter()
+                .position(|interface| interface == network_interface);
+            let alias = self
+                .config
+                .interface_aliases
+                .get(network_interface)
+                .map_or("", String::as_str);
+            column!(
+                padded_control(
+                    widget::settings::item::builder(
+                        self.get_interface_display_name(network_interface)
+                    )
+                    .description(network_interface)
+                    .control(button::standard(fl!("monitor")).on_press_maybe(
+                        index.map(|index| Message::UpdateSelectedNetworkInterface(index + 1))
+                    ))
+                ),
+                padded_control(widget::settings::item(
+                    fl!("alias"),
+                    widget::text_input(network_interface, alias)
+                        .on_input(move |alias| {
+                            Message::InterfaceAliasChanged(network_interface.to_string(), alias)
+                        })
+                        .width(160)
+                ))
+            )
+        };
+
+        if let Some((_, counters)) = self
+            .interface_counters
+            .iter()
+            .find(|(name, _)| name == network_interface)
+        {
+            let (download, upload) = self.format_rates(network_interface, counters);
+            let total = |received_bytes: u64, sent_bytes: u64| {
+                widget::text::body(self.format_usage(DailyUsage {
+                    received_bytes,
+                    sent_bytes,
+                }))
+            };
+            content = content
+                .push(
+                    padded_control(widget::divider::horizontal::default())
+                        .padding([space_xxs, space_s]),
+                )
+                .push(padded_control(
+                    column!(
+                        widget::settings::item(
+                            fl!("download"),
+                            widget::text::body(format!("{} {}", download.0, download.1))
+                        ),
+                        widget::settings::item(
+                            fl!("upload"),
+                            widget::text::body(format!("{} {}", upload.0, upload.1))
+                        ),
+                        widget::settings::item(
+                            fl!("since-boot"),
+                            total(counters.since_boot.0, counters.since_boot.1)
+                        ),
+                        widget::settings::item(
+                            fl!("this-session"),
+                            total(
+                                counters.session_totals.received_bytes,
+                                counters.session_totals.sent_bytes
+                            )
+                        ),
+                    )
+                    .spacing(space_xxxs),
+                ));
+        }
+
+        if !monitored {
+            return content.into();
+        }
+
+        let reachability = |reachable: Option<bool>| match reachable {
+            Some(true) => fl!("reachable"),
+            Some(false) => fl!("unreachable"),
+            None => fl!("unknown"),
+        };
+        let mut reachability_section = column!().spacing(space_xxxs);
+        if let Some((gateway, reachable)) = self.gateway {
+            reachability_section = reachability_section.push(widget::settings::item(
+                fl!("gateway", address = gateway.to_string()),
+                widget::text::body(reachability(reachable)),
+            ));
+        }
+        if self.config.show_reachability {
+            reachability_section = reachability_section.push(widget::settings::item(
+                fl!("internet"),
+                widget::text::body(reachability(self.online)),
+            ));
+        }
+        if self.gateway.is_some() || self.config.show_reachability {
+            content = content
+                .push(
+                    padded_control(widget::divider::horizontal::default())
+                        .padding([space_xxs, space_s]),
+                )
+                .push(padded_control(reachability_section));
+        }
+
+        if let Some((_, bssid)) = &self.bssid {
+            let mut access_point = column!(widget::settings::item(
+                fl!("access-point"),
+                widget::text::body(bssid.as_str())
+            ))
+            .spacing(space_xxxs);
+            for roam in &self.roaming_log {
+                access_point = access_point.push(widget::text::caption(roam.as_str()));
+            }
+            content = content
+                .push(
+                    padded_control(widget::divider::horizontal::default())
+                        .padding([space_xxs, space_s]),
+                )
+                .push(padded_control(access_point));
+        }
+
+        if let Some(limit) = &self.shaping_limit {
+            content = content
+                .push(
+                    padded_control(widget::divider::horizontal::default())
+                        .padding([space_xxs, space_s]),
+                )
+                .push(padded_control(widget::settings::item(
+                    fl!("shaping-limit"),
+                    widget::text::body(format!(
+                        "{} {}  ({} {})",
+                        limit.qdisc, limit.rate, self.upload_speed_display, self.upload_unit
+                    )),
+                )));
+        }
+
+        if self.config.show_shaping_control {
+            let enabled = !self.applying_shaping_limit;
+            content = content.push(padded_control(widget::settings::item(
+                fl!("limit-upload"),
+                row!(
+                    spin_button::spin_button(
+                        format!(
+                            "{} M{}/{}",
+                            self.config.shaping_rate,
+                            fl!("bits-short"),
+                            fl!("second-short")
+                        ),
+                        self.config.shaping_rate,
+                        1,
+                        1,
+                        10000,
+                        Message::ShapingRateChanged,
+                    ),
+                    button::standard(fl!("apply")).on_press_maybe(
+                        enabled.then_some(Message::SetShapingLimit(Some(self.config.shaping_rate)))
+                    ),
+                    button::standard(fl!("remove")).on_press_maybe(
+                        (enabled && self.shaping_limit.is_some())
+                            .then_some(Message::SetShapingLimit(None))
+                    ),
+                )
+                .spacing(space_xxs)
+                .align_y(Alignment::Center),
+            )));
+        }
+
+        content.into()
+    }
+
+    /// Popup page with the state of the whole system
+    fn view_overview_page(&self) -> Element<'_, Message> {
+        let Spacing {
+            space_xxxs,
+            space_xxs,
+            space_s,
+            ..
+        } = theme::active().cosmic().spacing;
+
+        let header = if self.network_interfaces.is_empty() {
+            fl!("not-connected")
+        } else {
+            fl!("interface-rates")
+        };
+        let mut interface_rates = column!(widget::text::heading(header)).spacing(space_xxxs);
+        for (network_interface, counters) in &self.interface_counters {
+            let (download, upload) = self.format_rates(network_interface, counters);
+            interface_rates = interface_rates.push(widget::settings::item(
+                self.get_interface_display_name(network_interface),
+                widget::text::body(format!(
+                    "{} {}  {} {}",
+                    download.0, download.1, upload.0, upload.1
+                )),
+            ));
+        }
+        let mut content = column!(padded_control(interface_rates));
+
+        if !self.devices.is_empty() {
+            let mut devices = column!(widget::text::heading(fl!("interfaces"))).spacing(space_xxxs);
+            for device in &self.devices {
+                let name = device.name.clone();
+                devices = devices.push(widget::settings::item(
+                    device.name.as_str(),
+                    toggler(device.connected).on_toggle(move |connected| {
+                        Message::SetDeviceConnected(name.clone(), connected)
+                    }),
+                ));
+            }
+            content = content
+                .push(
+                    padded_control(widget::divider::horizontal::default())
+                        .padding([space_xxs, space_s]),
+                )
+                .push(padded_control(devices));
+        }
+
+        let firewall_status = match &self.firewall_status {
+            Some(FirewallStatus {
+                backend,
+                zone: Some(zone),
+            }) => format!("{backend} ({zone})"),
+            Some(FirewallStatus { backend, .. }) => backend.to_string(),
+            None => fl!("inactive"),
+        };
+        content = content
+            .push(
+                padded_control(widget::divider::horizontal::default())
+                    .padding([space_xxs, space_s]),
+            )
+            .push(padded_control(widget::settings::item(
+                fl!("firewall"),
+                row!(
+                    widget::icon::from_name(if self.firewall_status.is_some() {
+                        "security-high-symbolic"
+                    } else {
+                        "security-low-symbolic"
+                    })
+                    .size(16)
+                    .icon(),
+                    widget::text::body(firewall_status),
+                )
+                .spacing(space_xxs)
+                .align_y(Alignment::Center),
+            )));
+
+        let mut listening_ports = column!(widget::settings::item(
+            fl!("listening-ports", count = self.listening_ports.len()),
+            button::text(if self.show_listening_ports {
+                fl!("hide")
+            } else {
+                fl!("show")
+            })
+            .on_press(Message::ToggleListeningPorts),
+        ))
+        .spacing(space_xxxs);
+        if self.show_listening_ports {
+            for port in &self.listening_ports {
+                listening_ports = listening_ports.push(widget::settings::item(
+                    format!("{} {}", port.protocol, port.port),
+                    widget::text::body(port.process.as_deref().unwrap_or("?")),
+                ));
+            }
+        }
+        content = content
+            .push(
+                padded_control(widget::divider::horizontal::default())
+                    .padding([space_xxs, space_s]),
+            )
+            .push(padded_control(listening_ports));
+
+        if !self.config.wake_on_lan_mac.is_empty() {
+            content = content
+                .push(
+                    padded_control(widget::divider::horizontal::default())
+                        .padding([space_xxs, space_s]),
+                )
+                .push(padded_control(widget::settings::item(
+                    fl!("wake-on-lan"),
+                    button::standard(fl!("wake-device")).on_press(Message::WakeOnLan),
+                )));
+        }
+
+        for (name, readings) in &self.plugin_readings {
+            let mut card = column!(widget::text::heading(name.as_str())).spacing(space_xxxs);
+            for reading in readings {
+                card = card.push(widget::settings::item(
+                    reading.label.as_str(),
+                    widget::text::body(reading.value.as_str()),
+                ));
+            }
+            content = content
+                .push(
+                    padded_control(widget::divider::horizontal::default())
+                        .padding([space_xxs, space_s]),
+                )
+                .push(padded_control(card));
+        }
+
+        content.into()
+    }
+
+    /// Usage of every hour of the week, over the hourly records of the
+    /// usage database
+    fn view_heatmap<'a>(&self, heatmap: &Heatmap) -> Element<'a, Message> {
+        let Spacing {
+            space_xxxs,
+            space_xxs,
+            ..
+        } = theme::active().cosmic().spacing;
+        let colors = self.config.palette.colors(theme::active().cosmic());
+
+        let mut weekdays = column!();
+        for weekday in 1..=7 {
+            weekdays = weekdays.push(
+                container(widget::text::caption(fl!("weekday", day = weekday)))
+                    .center_y(HEATMAP_CELL),
+            );
+        }
+        let mut hours = row!();
+        for hour in (0..24).step_by(6) {
+            hours = hours
+                .push(container(widget::text::caption(hour.to_string())).width(HEATMAP_CELL * 6.0));
+        }
+        let busiest = heatmap.iter().flatten().copied().max().unwrap_or(0);
+        let grid = row!(
+            weekdays,
+            column!(
+                widget::svg(graph::heatmap(heatmap, HEATMAP_CELL, colors.download))
+                    .width(HEATMAP_CELL * 24.0)
+                    .height(HEATMAP_CELL * 7.0),
+                hours,
+            ),
+        )
+        .spacing(space_xxs);
+
+        column!(
+            widget::text::heading(fl!("usage-by-hour")),
+            grid,
+            widget::settings::item(
+                fl!("busiest-hour"),
+                widget::text::body(self.format_size(busiest)),
+            ),
+        )
+        .spacing(space_xxxs)
+        .into()
+    }
+
+    /// Popup page with the usage of every hour of the week and of every
+    /// interface over the current month
+    fn view_usage_page(&self) -> Element<'_, Message> {
+        let Spacing {
+            space_xxxs,
+            space_xxs,
+            space_s,
+            ..
+        } = theme::active().cosmic().spacing;
+        let mut interfaces =
+            column!(widget::text::heading(fl!("this-month-by-interface"))).spacing(space_xxxs);
+        let mut interfaces_empty = true;
+        for (network_interface, usage) in &self.interface_usage {
+            let usage = usage.this_cycle(self.config.billing_day);
+            if usage.received_bytes == 0 && usage.sent_bytes == 0 {
+                continue;
+            }
+            interfaces_empty = false;
+            interfaces = interfaces.push(widget::settings::item(
+                self.get_interface_display_name(network_interface),
+                widget::text::body(self.format_usage(usage)),
+            ));
+        }
+
+        let mut content = column!();
+        if let Some(heatmap) = &self.heatmap {
+            content = content.push(padded_control(self.view_heatmap(heatmap)));
+        }
+        if !interfaces_empty {
+            if self.heatmap.is_some() {
+                content = content.push(
+                    padded_control(widget::divider::horizontal::default())
+                        .padding([space_xxs, space_s]),
+                );
+            }
+            content = content.push(padded_control(interfaces));
+        }
+        if self.heatmap.is_none() && interfaces_empty {
+            content = content.push(padded_control(widget::text::body(fl!("no-usage"))));
+        }
+        content.into()
+    }
+
+    /// Popup page with the applet settings
+    fn view_settings_page(&self) -> Element<'_, Message> {
+        let Spacing {
+            space_xxxs,
+            space_xxs,
+            space_s,
+            ..
+        } = theme::active().cosmic().spacing;
+        let update_rate = self.config.get_update_interval().as_millis() as u32;
+        let mut content = column!(
+            padded_control(widget::settings::item(
+                fl!("aggregate-interfaces"),
+                toggler(self.config.aggregate_interfaces)
+                    .on_toggle(Message::AggregateInterfacesChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("link-preference"),
+                dropdown(
+                    &self.link_preference_options[..],
+                    Some(self.config.link_preference as usize),
+                    Message::LinkPreferenceChanged
+                )
+            )),
+            padded_control(widget::settings::item(
+                fl!("prefer-vpn"),
+                toggler(self.config.prefer_vpn).on_toggle(Message::PreferVpnChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("include-virtual-interfaces"),
+                toggler(self.config.include_virtual_interfaces)
+                    .on_toggle(Message::IncludeVirtualInterfacesChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("network-interface"),
+                dropdown(
+                    std::iter::once(fl!("automatic"))
+                        .chain(
+                            self.network_interfaces
+                                .iter()
+                                .map(|name| self.get_interface_display_name(name))
+                        )
+                        .collect::<Vec<_>>(),
+                    Some(
+                        self.config
+                            .network_interface
+                            .as_ref()
+                            .and_then(|name| {
+                                self.network_interfaces
+                                    .iter()
+                                    .position(|interface| interface == name)
+                            })
+                            .map_or(0, |index| index + 1)
+                    ),
+                    Message::UpdateSelectedNetworkInterface
+                )
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(
+                column!(
+                    widget::text::body(fl!("download-unit")),
+                    segmented_control::horizontal(&self.unit_model)
+                        .on_activate(Message::UnitChanged),
+                    widget::text::body(fl!("upload-unit")),
+                    segmented_control::horizontal(&self.upload_unit_model)
+                        .on_activate(Message::UploadUnitChanged)
+                )
+                .spacing(space_xxxs)
+            ),
+            padded_control(widget::settings::item(
+                fl!("show-both-units"),
+                toggler(self.config.show_both_units).on_toggle(Message::ShowBothUnitsChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("unit-scale"),
+                dropdown(
+                    &self.unit_scale_options[..],
+                    Some(self.config.unit_scale as usize),
+                    Message::UnitScaleChanged
+                )
+            )),
+            padded_control(widget::settings::item(
+                fl!("iec-labels"),
+                toggler(self.config.iec_labels).on_toggle(Message::IecLabelsChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("display-mode"),
+                row!(
+                    dropdown(
+                        &self.display_mode_options[..],
+                        Some(self.config.display_mode as usize),
+                        Message::DisplayModeChanged
+                    ),
+                    button::standard(fl!("reset")).on_press_maybe(
+                        (self.config.display_mode == DisplayMode::Total)
+                            .then_some(Message::ResetSessionTotals)
+                    ),
+                )
+                .spacing(space_xxs)
+                .align_y(Alignment::Center)
+            )),
+            padded_control(widget::settings::item(
+                fl!("fixed-prefix"),
+                dropdown(
+                    &self.fixed_prefix_options[..],
+                    Some(
+                        self.config
+                            .fixed_prefix
+                            .map_or(0, |prefix| prefix.exponent() as usize + 1)
+                    ),
+                    Message::FixedPrefixChanged
+                )
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("update-rate"),
+                spin_button::spin_button(
+                    format!("{update_rate} {}", fl!("millisecond-short")),
+                    update_rate,
+                    250,
+                    MIN_UPDATE_RATE,
+                    MAX_UPDATE_RATE,
+                    Message::UpdateRateChanged,
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("stats-backend"),
+                dropdown(
+                    &self.stats_backend_options[..],
+                    Some(self.config.stats_backend as usize),
+                    Message::StatsBackendChanged
+                )
+            )),
+            padded_control(widget::settings::item(
+                fl!("show-peak"),
+                toggler(self.config.show_peak).on_toggle(Message::ShowPeakChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("peak-window"),
+                spin_button::spin_button(
+                    format!("{} {}", self.config.peak_window, fl!("second-short")),
+                    self.config.peak_window,
+                    10,
+                    10,
+                    3600,
+                    Message::PeakWindowChanged,
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("show-sparkline"),
+                toggler(self.config.show_sparkline).on_toggle(Message::ShowSparklineChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("sparkline-length"),
+                spin_button::spin_button(
+                    fl!("update-count", count = self.config.sparkline_length),
+                    self.config.sparkline_length,
+                    10,
+                    10,
+                    (history::FULL_RESOLUTION_AGE * 1000 / update_rate).max(10) as u16,
+                    Message::SparklineLengthChanged,
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("graph-log-scale"),
+                toggler(self.config.graph_log_scale).on_toggle(Message::GraphLogScaleChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("graph-style"),
+                dropdown(
+                    &self.graph_style_options[..],
+                    Some(self.config.graph_style as usize),
+                    Message::GraphStyleChanged
+                )
+            )),
+            padded_control(widget::settings::item(
+                fl!("history-retention"),
+                dropdown(
+                    &self.history_retention_options[..],
+                    HISTORY_RETENTIONS
+                        .iter()
+                        .position(|retention| *retention == self.config.history_retention),
+                    Message::HistoryRetentionChanged
+                )
+            )),
+            padded_control(widget::settings::item(
+                fl!("usage-retention"),
+                spin_button::spin_button(
+                    fl!("day-count", count = self.config.usage_retention),
+                    self.config.usage_retention,
+                    30,
+                    30,
+                    3650,
+                    Message::UsageRetentionChanged,
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("import-vnstat"),
+                button::standard(fl!("import")).on_press_maybe(
+                    self.usage_database
+                        .is_some()
+                        .then_some(Message::ImportVnstat)
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("data-cap"),
+                spin_button::spin_button(
+                    if self.config.data_cap == 0 {
+                        fl!("off")
+                    } else {
+                        format!(
+                            "{} {}{}",
+                            self.config.data_cap,
+                            self.get_unit_prefix(3, self.config.unit_scale),
+                            fl!("bytes-short")
+                        )
+                    },
+                    self.config.data_cap,
+                    5,
+                    0,
+                    100000,
+                    Message::DataCapChanged,
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("data-cap-thresholds"),
+                widget::text_input("80, 100", &self.data_cap_thresholds_input)
+                    .on_input(Message::DataCapThresholdsInput)
+                    .width(120)
+            )),
+            padded_control(widget::settings::item(
+                fl!("billing-day"),
+                spin_button::spin_button(
+                    self.config.billing_day.to_string(),
+                    self.config.billing_day,
+                    1,
+                    1,
+                    31,
+                    Message::BillingDayChanged,
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("smoothing"),
+                spin_button::spin_button(
+                    fl!("smoothing-samples", count = self.config.smoothing),
+                    self.config.smoothing,
+                    1,
+                    1,
+                    10,
+                    Message::SmoothingChanged,
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("minimum-rate"),
+                spin_button::spin_button(
+                    {
+                        let (display, unit) =
+                            self.format_rate(self.config.minimum_rate as u64, Unit::Bytes, "");
+                        format!("{display} {unit}")
+                    },
+                    self.config.minimum_rate,
+                    1024,
+                    0,
+                    1024 * 1024,
+                    Message::MinimumRateChanged,
+                ),
+            )),
+            padded_control(widget::settings::item(
+                fl!("hide-below-minimum"),
+                toggler(self.config.hide_below_minimum).on_toggle(Message::HideBelowMinimumChanged)
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("show-download-speed"),
+                toggler(self.config.show_download_speed)
+                    .on_toggle(Message::ShowDownloadSpeedChanged)
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("show-upload-speed"),
+                toggler(self.config.show_upload_speed).on_toggle(Message::ShowUploadSpeedChanged)
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("tabular-digits"),
+                toggler(self.config.tabular_digits).on_toggle(Message::TabularDigitsChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("upload-first"),
+                toggler(self.config.upload_first).on_toggle(Message::UploadFirstChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("compact"),
+                toggler(self.config.compact).on_toggle(Message::CompactChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("show-unit"),
+                toggler(self.config.show_unit).on_toggle(Message::ShowUnitChanged)
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("download-symbol"),
+                widget::text_input("", &self.config.download_symbol)
+                    .on_input(Message::DownloadSymbolChanged)
+                    .width(80)
+            )),
+            padded_control(widget::settings::item(
+                fl!("upload-symbol"),
+                widget::text_input("", &self.config.upload_symbol)
+                    .on_input(Message::UploadSymbolChanged)
+                    .width(80)
+            )),
+            padded_control(widget::settings::item(
+                fl!("symbol-before-value"),
+                toggler(self.config.symbol_before_value)
+                    .on_toggle(Message::SymbolBeforeValueChanged)
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("interface-label"),
+                dropdown(
+                    &self.interface_label_options[..],
+                    Some(self.config.interface_label as usize),
+                    Message::InterfaceLabelChanged
+                )
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("interface-coloring"),
+                dropdown(
+                    &self.interface_coloring_options[..],
+                    Some(self.config.interface_coloring as usize),
+                    Message::InterfaceColoringChanged
+                )
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("palette"),
+                dropdown(
+                    &self.palette_options[..],
+                    Some(self.config.palette as usize),
+                    Message::PaletteChanged
+                )
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(widget::settings::item(
+                fl!("show-reachability"),
+                toggler(self.config.show_reachability).on_toggle(Message::ShowReachabilityChanged)
+            )),
+            padded_control(widget::settings::item(
+                fl!("reachability-target"),
+                widget::text_input("", &self.config.reachability_target)
+                    .on_input(Message::ReachabilityTargetChanged)
+                    .width(160)
+            )),
+            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
+            padded_control(
+                widget::settings::item::builder(fl!("event-command"))
+                    .description(fl!("event-command-description"))
+                    .control(
+                        widget::text_input("", &self.config.event_command)
+                            .on_input(Message::EventCommandChanged)
+                            .width(160)
+                    )
+            ),
+            padded_control(widget::settings::item(
+                fl!("webhook-url"),
+                widget::text_input("https://", &self.config.webhook_url)
+                    .on_input(Message::WebhookUrlChanged)
+                    .width(160)
+            )),
+            padded_control(widget::settings::item(
+                fl!("wake-on-lan-mac"),
+                widget::text_input("00:00:00:00:00:00", &self.config.wake_on_lan_mac)
+                    .on_input(Message::WakeOnLanMacChanged)
+                    .width(160)
+            ))
+        );
+
+        let mut excluded_interfaces =
+            column!(widget::text::heading(fl!("excluded-interfaces"))).spacing(space_xxxs);
+        for (index, pattern) in self.config.excluded_interfaces.iter().enumerate() {
+            excluded_interfaces = excluded_interfaces.push(widget::settings::item(
+                pattern.as_str(),
+                button::text(fl!("remove")).on_press(Message::RemoveExclusionPattern(index)),
+            ));
+        }
+        excluded_interfaces = excluded_interfaces.push(
+            row!(
+                widget::text_input(fl!("exclusion-pattern"), &self.exclusion_pattern)
+                    .on_input(Message::ExclusionPatternInput),
+                button::standard(fl!("add")).on_press_maybe(
+                    (!self.exclusion_pattern.trim().is_empty())
+                        .then_some(Message::AddExclusionPattern)
+                )
+            )
+            .spacing(space_xxs)
+            .align_y(Alignment::Center),
+        );
+        content = content
+            .push(
+                padded_control(widget::divider::horizontal::default())
+                    .padding([space_xxs, space_s]),
+            )
+            .push(padded_control(excluded_interfaces));
+
+        let mut panel_interfaces =
+            column!(widget::text::heading(fl!("panel-interfaces"))).spacing(space_xxxs);
+        let configured = self
+            .config
+            .panel_interfaces
+            .iter()
+            .filter(|name| !self.network_interfaces.contains(name));
+        for name in self.network_interfaces.iter().chain(configured) {
+            let network_interface = name.clone();
+            panel_interfaces = panel_interfaces.push(widget::settings::item(
+                self.get_interface_display_name(name),
+                toggler(self.config.panel_interfaces.contains(name)).on_toggle(move |show| {
+                    Message::PanelInterfaceToggled(network_interface.clone(), show)
+                }),
+            ));
+        }
+        content = content
+            .push(
+                padded_control(widget::divider::horizontal::default())
+                    .padding([space_xxs, space_s]),
+            )
+            .push(padded_control(panel_interfaces));
+
+        content.into()
+    }
+}
+
+impl cosmic::Application for AppModel {
+    type Executor = cosmic::executor::Default;
+
+    type Flags = ();
+
+    type Message = Message;
+
+    const APP_ID: &'static str = "io.github.avomar.cosmic-ext-applet-bitrate";
+
+    fn core(&self) -> &cosmic::Core {
+        &self.core
     }
 
     fn core_mut(&mut self) -> &mut cosmic::Core {
@@ -357,77 +3089,209 @@ impl cosmic::Application for AppModel {
     ) -> (Self, cosmic::Task<cosmic::Action<Self::Message>>) {
         let config_helper =
             cosmic_config::Config::new(Self::APP_ID, BitrateAppletConfig::VERSION).unwrap();
-        let config = cosmic_config::Config::new(Self::APP_ID, BitrateAppletConfig::VERSION)
+        let mut config = cosmic_config::Config::new(Self::APP_ID, BitrateAppletConfig::VERSION)
             .map(|context| match BitrateAppletConfig::get_entry(&context) {
                 Ok(config) => config,
                 Err((_errors, config)) => config,
             })
             .unwrap_or_default();
+        config.migrate_update_rate(&config_helper);
 
         let mut bits_entity = segmented_button::Entity::default();
         let mut bytes_entity = segmented_button::Entity::default();
+        let mut packets_entity = segmented_button::Entity::default();
         let mut unit_model = segmented_button::SingleSelectModel::builder()
             .insert(|b| b.text(fl!("bits")).with_id(|id| bits_entity = id))
             .insert(|b| b.text(fl!("bytes")).with_id(|id| bytes_entity = id))
+            .insert(|b| b.text(fl!("packets")).with_id(|id| packets_entity = id))
             .build();
 
         if config.unit == Unit::Bits {
             unit_model.activate(bits_entity);
         } else if config.unit == Unit::Bytes {
             unit_model.activate(bytes_entity);
+        } else if config.unit == Unit::Packets {
+            unit_model.activate(packets_entity);
         }
 
-        // Set initial received and sent bytes
-        let network_interfaces = network::get_network_interfaces();
-        let mut selected_network_interface: Option<usize> = None;
-        let mut received_bytes = 0;
-        let mut sent_bytes = 0;
-        if let Some(interface) = network_interfaces.get(0) {
-            selected_network_interface = Some(0);
-            received_bytes = network::get_received_bytes(interface).unwrap_or(0);
-            sent_bytes = network::get_sent_bytes(interface).unwrap_or(0);
+        let mut upload_bits_entity = segmented_button::Entity::default();
+        let mut upload_bytes_entity = segmented_button::Entity::default();
+        let mut upload_packets_entity = segmented_button::Entity::default();
+        let mut upload_unit_model = segmented_button::SingleSelectModel::builder()
+            .insert(|b| b.text(fl!("bits")).with_id(|id| upload_bits_entity = id))
+            .insert(|b| b.text(fl!("bytes")).with_id(|id| upload_bytes_entity = id))
+            .insert(|b| {
+                b.text(fl!("packets"))
+                    .with_id(|id| upload_packets_entity = id)
+            })
+            .build();
+
+        if config.get_upload_unit() == Unit::Bits {
+            upload_unit_model.activate(upload_bits_entity);
+        } else if config.get_upload_unit() == Unit::Bytes {
+            upload_unit_model.activate(upload_bytes_entity);
+        } else if config.get_upload_unit() == Unit::Packets {
+            upload_unit_model.activate(upload_packets_entity);
         }
 
+        let stats = stats::get_stats_source(config.stats_backend);
+        let data_sources = plugin::get_data_sources(&config.plugins);
+        let data_cap_thresholds_input = config
+            .data_cap_thresholds
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut history = History::new(config.history_retention);
+        let mut usage = UsageAccounting::default();
+        let mut interface_usage = BTreeMap::new();
+        persistence::load(Self::APP_ID, &mut history);
+        let usage_database = match UsageDatabase::open(Self::APP_ID) {
+            Ok(mut usage_database) => {
+                if let Err(why) = usage_database
+                    .compact(config.hourly_usage_retention, config.usage_retention)
+                    .and_then(|()| usage_database.load(&mut usage))
+                    .and_then(|()| usage_database.load_interfaces(&mut interface_usage))
+                {
+                    eprintln!("error while reading usage database: {why}");
+                }
+                Some(usage_database)
+            }
+            Err(why) => {
+                eprintln!("error while opening usage database: {why}");
+                None
+            }
+        };
+
+        let interface_font = match CosmicTk::get_entry(
+            &Config::new("com.system76.CosmicTk", CosmicTk::VERSION).unwrap(),
+        ) {
+            Ok(cosmic_tk) => cosmic_tk.interface_font,
+            Err((_, cosmic_tk)) => cosmic_tk.interface_font,
+        };
+
         // Construct the app model with the runtime's core.
         let mut app = AppModel {
             core,
             config_helper,
             config,
             popup: None,
-            received_bytes,
-            sent_bytes,
-            download_speed: 0,
+            // Measured from the counters of the interface selected below
+            monitored: InterfaceCounters {
+                usage,
+                ..InterfaceCounters::from_reading(None, None)
+            },
+            peak_samples: VecDeque::new(),
+            interface_usage,
+            usage_database,
+            history,
+            session_peaks: (None, None),
+            interface_changes: VecDeque::new(),
             download_speed_display: "".to_string(),
             download_unit: "".to_string(),
-            upload_speed: 0,
+            download_counterpart: None,
             upload_speed_display: "".to_string(),
             upload_unit: "".to_string(),
-            network_interfaces: network_interfaces,
-            selected_network_interface,
+            upload_counterpart: None,
+            panel_interfaces: Vec::new(),
+            interface_counters: Vec::new(),
+            network_interfaces: Vec::new(),
+            selected_network_interface: None,
+            selected_ifindex: None,
             unit_model,
             bits_entity,
             bytes_entity,
+            packets_entity,
+            upload_unit_model,
+            popup_tabs: segmented_button::SingleSelectModel::default(),
+            upload_bits_entity,
+            upload_bytes_entity,
+            upload_packets_entity,
             rectangle: Rectangle::default(),
             rectangle_tracker: None,
             font_system: FontSystem::new(),
             unit_width: 0.0,
             data_width: 0.0,
             line_height: 0.0,
+            dot_width: 0.0,
+            warning_width: 0.0,
+            today_width: 0.0,
+            link_local_only: false,
+            interface_font: interface_font.clone(),
+            interface_label: String::new(),
+            interface_label_width: 0.0,
+            label_flashes: 0,
+            scroll_offset: 0.0,
+            scrolled_interface: None,
+            interface_label_options: vec![fl!("none"), fl!("name"), fl!("abbreviation")],
+            unit_scale_options: vec![fl!("unit-scale-iec"), fl!("unit-scale-si")],
+            display_mode_options: vec![fl!("rates"), fl!("session-totals"), fl!("daily-totals")],
+            fixed_prefix_options: vec![
+                fl!("automatic"),
+                fl!("none"),
+                fl!("kilo"),
+                fl!("mega"),
+                fl!("giga"),
+                fl!("tera"),
+            ],
+            history_retention_options: HISTORY_RETENTIONS
+                .iter()
+                .map(|retention| format_duration(*retention))
+                .collect(),
+            interface_coloring_options: vec![fl!("none"), fl!("text"), fl!("dot")],
+            graph_style_options: vec![fl!("lines"), fl!("stacked")],
+            palette_options: vec![
+                fl!("palette-theme"),
+                fl!("palette-red-green-safe"),
+                fl!("palette-blue-yellow-safe"),
+            ],
+            link_preference_options: vec![fl!("any"), fl!("wired"), fl!("wireless")],
+            stats_backend_options: vec![
+                fl!("stats-backend-netlink"),
+                fl!("stats-backend-sysfs"),
+                fl!("stats-backend-proc-net-dev"),
+            ],
+            online: None,
+            devices: Vec::new(),
+            bssid: None,
+            roaming_log: Vec::new(),
+            gateway: None,
+            connection_name: None,
+            reconnecting: false,
+            firewall_status: None,
+            shaping_limit: None,
+            applying_shaping_limit: false,
+            listening_ports: Vec::new(),
+            exclusion_pattern: String::new(),
+            data_cap_thresholds_input,
+            data_cap_notified: None,
+            graph_hover: None,
+            graph_range: GRAPH_RANGES[1],
+            show_listening_ports: false,
+            #[cfg(feature = "otlp")]
+            telemetry: crate::telemetry::Telemetry::new(Self::APP_ID),
+            stats,
+            data_sources,
+            plugin_readings: Vec::new(),
+            polling_plugins: false,
         };
+        // Set initial received and sent bytes
+        app.network_interfaces = app.get_network_interfaces();
+        app.update_interface_counters_list();
+        app.select_preferred_network_interface();
         app.set_download_speed_display();
         app.set_upload_speed_display();
-        let interface_font = match CosmicTk::get_entry(
-            &Config::new("com.system76.CosmicTk", CosmicTk::VERSION).unwrap(),
-        ) {
-            Ok(cosmic_tk) => cosmic_tk.interface_font,
-            Err((_, cosmic_tk)) => cosmic_tk.interface_font,
-        };
-        app.data_width = app.get_text_width_and_height("00.00", &interface_font).0;
-        app.unit_width = app.get_text_width_and_height("Mb/s  ↓", &interface_font).0;
-        app.line_height = app
-            .get_text_width_and_height("1234567890.KM/Bb↓↑", &interface_font)
-            .1;
-        (app, cosmic::Task::none())
+        app.update_text_metrics(&interface_font);
+        app.update_link_local_only();
+        app.update_popup_tabs();
+
+        let mut tasks = Vec::new();
+        if app.config.show_reachability {
+            tasks.push(cosmic::task::message(cosmic::Action::App(
+                Message::ProbeReachability,
+            )));
+        }
+        (app, cosmic::Task::batch(tasks))
     }
 
     fn on_close_requested(&self, id: window::Id) -> Option<Message> {
@@ -451,11 +3315,25 @@ impl cosmic::Application for AppModel {
         let autosize_id: widget::Id;
         if is_horizontal && (self.config.show_download_speed || self.config.show_upload_speed) {
             autosize_id = AUTOSIZE_MAIN_ID.clone();
-            button = button::custom(self.horizontal_layout())
+            let panel_button = button::custom(self.horizontal_layout())
                 .padding(0)
                 .on_press_down(Message::TogglePopup)
-                .class(cosmic::theme::Button::AppletIcon)
-                .into();
+                .class(cosmic::theme::Button::AppletIcon);
+            // Units hidden from the panel and peaks are found in the tooltip
+            button = if self.config.show_unit && !self.config.show_peak {
+                panel_button.into()
+            } else {
+                self.core
+                    .applet
+                    .applet_tooltip::<Message>(
+                        panel_button,
+                        self.get_tooltip(),
+                        self.popup.is_some(),
+                        Message::Surface,
+                        None,
+                    )
+                    .into()
+            };
         } else {
             autosize_id = AUTOSIZE_ICON_BTN_ID.clone();
             button = self
@@ -467,13 +3345,7 @@ impl cosmic::Application for AppModel {
                         .icon_button(Self::APP_ID)
                         .on_press_down(Message::TogglePopup)
                         .class(cosmic::theme::Button::AppletIcon),
-                    format!(
-                        "{} {}  {} {}",
-                        self.download_speed_display,
-                        self.download_unit,
-                        self.upload_speed_display,
-                        self.upload_unit
-                    ),
+                    self.get_tooltip(),
                     self.popup.is_some(),
                     Message::Surface,
                     None,
@@ -481,6 +3353,10 @@ impl cosmic::Application for AppModel {
                 .into();
         }
 
+        let button: Element<'_, Self::Message> = iced::widget::mouse_area(button)
+            .on_scroll(Message::Scrolled)
+            .into();
+
         autosize::autosize(
             if let Some(tracker) = self.rectangle_tracker.as_ref() {
                 tracker.container(0, button).ignore_bounds(true).into()
@@ -494,68 +3370,39 @@ impl cosmic::Application for AppModel {
     }
 
     fn view_window(&self, _id: window::Id) -> Element<'_, Self::Message> {
-        let Spacing {
-            space_xxxs,
-            space_xxs,
-            space_s,
-            ..
-        } = theme::active().cosmic().spacing;
+        let page = match self.popup_tabs.active_data::<PopupTab>() {
+            Some(PopupTab::Interface(network_interface)) => {
+                self.view_interface_page(network_interface)
+            }
+            Some(PopupTab::Usage) => self.view_usage_page(),
+            Some(PopupTab::Settings) => self.view_settings_page(),
+            _ => self.view_overview_page(),
+        };
         let content = column!(
-            padded_control(widget::settings::item(
-                fl!("network-interface"),
-                dropdown(
-                    self.network_interfaces.clone(),
-                    self.selected_network_interface,
-                    Message::UpdateSelectedNetworkInterface
-                )
-            )),
-            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
             padded_control(
-                column!(
-                    widget::text::body(fl!("unit")),
-                    segmented_control::horizontal(&self.unit_model)
-                        .on_activate(Message::UnitChanged)
-                )
-                .spacing(space_xxxs)
+                segmented_control::horizontal(&self.popup_tabs)
+                    .on_activate(Message::PopupTabActivated)
             ),
-            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
-            padded_control(widget::settings::item(
-                fl!("update-rate"),
-                spin_button::spin_button(
-                    format!("{} {}", self.config.update_rate, fl!("second-short")),
-                    self.config.update_rate,
-                    1,
-                    1,
-                    10,
-                    Message::UpdateRateChanged,
-                ),
-            )),
-            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
-            padded_control(widget::settings::item(
-                fl!("show-download-speed"),
-                toggler(self.config.show_download_speed)
-                    .on_toggle(Message::ShowDownloadSpeedChanged)
-            )),
-            padded_control(widget::divider::horizontal::default()).padding([space_xxs, space_s]),
-            padded_control(widget::settings::item(
-                fl!("show-upload-speed"),
-                toggler(self.config.show_upload_speed).on_toggle(Message::ShowUploadSpeedChanged)
-            ))
-        )
-        .padding([8, 0]);
+            page
+        );
 
-        self.core.applet.popup_container(content).into()
+        self.core
+            .applet
+            .popup_container(content.padding([8, 0]))
+            .into()
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        Subscription::batch(vec![
+        let mut subscriptions = vec![
             rectangle_tracker_subscription(0).map(|e| Message::Rectangle(e.1)),
-            (iced::time::every(tokio::time::Duration::from_secs(
-                self.config.update_rate as u64,
-            )))
-            .map(|_| Message::UpdateBandwidth),
+            (iced::time::every(self.config.get_update_interval()))
+                .map(|_| Message::UpdateBandwidth),
             (iced::time::every(tokio::time::Duration::from_secs(5)))
                 .map(|_| Message::UpdateNetworkInterfaces),
+            Subscription::run(link_changes),
+            Subscription::run(termination),
+            iced::time::every(tokio::time::Duration::from_secs(SAVE_INTERVAL))
+                .map(|_| Message::SaveState),
             // Watch for application configuration changes.
             self.core()
                 .watch_config::<BitrateAppletConfig>(Self::APP_ID)
@@ -563,95 +3410,532 @@ impl cosmic::Application for AppModel {
             self.core()
                 .watch_config("com.system76.CosmicTk")
                 .map(|u| Message::ThemeChanged(u.config)),
-        ])
+        ];
+        if self.config.announce_interval > 0 {
+            subscriptions.push(
+                iced::time::every(tokio::time::Duration::from_secs(
+                    self.config.announce_interval as u64,
+                ))
+                .map(|_| Message::Announce),
+            );
+        }
+        // Applets get keyboard input only while their popup is open
+        if self.popup.is_some() {
+            subscriptions.push(iced::keyboard::on_key_press(|key, modifiers| {
+                (modifiers.control() && matches!(key.as_ref(), iced::keyboard::Key::Character("r")))
+                    .then_some(Message::Announce)
+            }));
+        }
+        if self.config.show_reachability {
+            subscriptions.push(
+                iced::time::every(tokio::time::Duration::from_secs(
+                    self.config.reachability_interval.max(1) as u64,
+                ))
+                .map(|_| Message::ProbeReachability),
+            );
+        }
+        // Readings are only shown in the popup
+        if self.popup.is_some() && !self.data_sources.is_empty() {
+            subscriptions.push(
+                iced::time::every(tokio::time::Duration::from_secs(PLUGIN_POLL_INTERVAL))
+                    .map(|_| Message::PollPlugins),
+            );
+        }
+        Subscription::batch(subscriptions)
     }
 
     fn update(&mut self, message: Self::Message) -> cosmic::Task<cosmic::Action<Self::Message>> {
         match message {
             Message::UpdateBandwidth => {
-                if let Some(selected_network_interface) = self.selected_network_interface {
-                    let network_interface =
-                        self.network_interfaces[selected_network_interface].clone();
-                    if let Some(received_bytes_cur) =
-                        network::get_received_bytes(network_interface.as_ref())
-                    {
-                        self.download_speed = received_bytes_cur - self.received_bytes;
-                        if self.config.unit == Unit::Bits {
-                            self.download_speed *= 8;
-                        }
-                        self.download_speed /= self.config.update_rate as u64;
-                        self.received_bytes = received_bytes_cur;
-                        self.set_download_speed_display();
+                self.stats.refresh();
+                self.update_interface_counters();
+                if self.config.aggregate_interfaces || self.selected_network_interface.is_some() {
+                    #[cfg(feature = "otlp")]
+                    let network_interface = self
+                        .selected_network_interface_name()
+                        .filter(|_| !self.config.aggregate_interfaces)
+                        .unwrap_or_else(|| String::from("all"));
+                    let counters = self.get_counters();
+                    // Sums of counters wrap around at no known value
+                    let limit = self
+                        .stats
+                        .counter_limit()
+                        .filter(|_| !self.config.aggregate_interfaces);
+                    #[cfg(feature = "otlp")]
+                    let elapsed = self.monitored.read_at.elapsed();
+                    let (received, sent) = self.monitored.add_reading(
+                        counters,
+                        limit,
+                        Instant::now(),
+                        self.config.smoothing as usize,
+                    );
+                    #[cfg(feature = "otlp")]
+                    if let Some(telemetry) = &self.telemetry {
+                        telemetry.record(
+                            &network_interface,
+                            crate::telemetry::Direction::Receive,
+                            received,
+                            elapsed,
+                        );
+                        telemetry.record(
+                            &network_interface,
+                            crate::telemetry::Direction::Transmit,
+                            sent,
+                            elapsed,
+                        );
                     }
-                    if let Some(sent_bytes_cur) =
-                        network::get_sent_bytes(network_interface.as_ref())
-                    {
-                        self.upload_speed = sent_bytes_cur - self.sent_bytes;
-                        if self.config.unit == Unit::Bits {
-                            self.upload_speed *= 8;
-                        }
-                        self.upload_speed /= self.config.update_rate as u64;
-                        self.sent_bytes = sent_bytes_cur;
-                        self.set_upload_speed_display();
+                    if let Some(usage_database) = &mut self.usage_database {
+                        usage_database.record(received, sent);
+                    }
+                    self.set_download_speed_display();
+                    self.set_upload_speed_display();
+                    self.record_peak_sample();
+                    self.check_data_cap();
+                } else {
+                    self.monitored.download_speed = 0;
+                    self.monitored.upload_speed = 0;
+                    self.monitored.download_packet_speed = 0;
+                    self.monitored.upload_packet_speed = 0;
+                }
+                self.history
+                    .push(self.monitored.download_speed, self.monitored.upload_speed);
+                if let Some(sample) = self.history.latest(1).next().copied() {
+                    let (download, upload) = &mut self.session_peaks;
+                    if download.is_none_or(|peak| sample.received > peak.received) {
+                        *download = Some(sample);
+                    }
+                    if upload.is_none_or(|peak| sample.sent > peak.sent) {
+                        *upload = Some(sample);
+                    }
+                }
+            }
+            Message::SaveState => self.save_state(),
+            Message::Export => {
+                let title = fl!("export-history");
+                return cosmic::Task::perform(
+                    async move {
+                        file_chooser::save::Dialog::new()
+                            .title(title)
+                            .file_name("bitrate.csv")
+                            .save_file()
+                            .await
+                            .ok()
+                            .and_then(|response| response.url()?.to_file_path().ok())
+                    },
+                    |path| cosmic::Action::App(Message::ExportTo(path)),
+                );
+            }
+            Message::ExportTo(path) => {
+                if let Some(path) = path
+                    && let Err(why) = export::write_csv(&path, &self.history, &self.monitored.usage)
+                {
+                    eprintln!("error while exporting history: {why}");
+                }
+            }
+            Message::SaveGraph => {
+                let title = fl!("save-graph");
+                return cosmic::Task::perform(
+                    async move {
+                        file_chooser::save::Dialog::new()
+                            .title(title)
+                            .file_name("bitrate.png")
+                            .save_file()
+                            .await
+                            .ok()
+                            .and_then(|response| response.url()?.to_file_path().ok())
+                    },
+                    |path| cosmic::Action::App(Message::SaveGraphTo(path)),
+                );
+            }
+            Message::SaveGraphTo(path) => {
+                if let Some(path) = path {
+                    let theme = theme::active();
+                    let cosmic = theme.cosmic();
+                    let (chart, scale) = self.get_graph_chart();
+                    let image = chart.background(cosmic.bg_color().into()).render(
+                        scale as f32 / self.get_history_unit().convert(1, 0) as f32,
+                        None,
+                        &self.config.palette.colors(cosmic),
+                        cosmic.on_bg_color().into(),
+                    );
+                    if let Err(why) = export::write_image(&path, &image) {
+                        eprintln!("error while saving graph: {why}");
                     }
-                } else {
-                    self.download_speed = 0;
-                    self.upload_speed = 0;
                 }
             }
+            Message::ImportVnstat => {
+                let title = fl!("import-vnstat");
+                return cosmic::Task::perform(
+                    async move {
+                        file_chooser::open::Dialog::new()
+                            .title(title)
+                            .open_file()
+                            .await
+                            .ok()
+                            .and_then(|response| response.url().to_file_path().ok())
+                    },
+                    |path| cosmic::Action::App(Message::ImportVnstatFrom(path)),
+                );
+            }
+            Message::ImportVnstatFrom(path) => {
+                if let Some(path) = path {
+                    self.import_vnstat(&path);
+                }
+            }
+            Message::GraphHovered(x) => self.graph_hover = x,
+            Message::GraphRangeChanged(range) => self.graph_range = range,
+            Message::GraphZoomed(delta) => {
+                let steps = match delta {
+                    iced::mouse::ScrollDelta::Lines { y, .. } => y,
+                    iced::mouse::ScrollDelta::Pixels { y, .. } => y / SCROLL_STEP_PIXELS,
+                };
+                // Scrolling up zooms in, down to the whole history
+                let range = self.graph_range as f32 * GRAPH_ZOOM_STEP.powf(-steps);
+                self.graph_range = (range.round() as u32).clamp(
+                    GRAPH_RANGES[0],
+                    self.config.history_retention.max(GRAPH_RANGES[0]),
+                );
+            }
+            Message::Terminate => {
+                self.save_state();
+                std::process::exit(0);
+            }
             Message::UpdateNetworkInterfaces => {
-                if let Some(selected_interface) = self.selected_network_interface {
-                    let selected_network_interface = self
-                        .network_interfaces
-                        .get(selected_interface)
-                        .unwrap()
-                        .clone();
-                    self.network_interfaces = network::get_network_interfaces();
-                    if let Some(selected_interface_index) = self
-                        .network_interfaces
-                        .iter()
-                        .position(|interface| &selected_network_interface == interface)
-                    {
-                        self.selected_network_interface = Some(selected_interface_index);
-                    } else {
-                        self.select_default_network_interface();
+                // Links changed since the snapshot of the latest update
+                self.stats.refresh();
+                let previous_interface = self.selected_network_interface_name();
+                let network_interfaces = self.get_network_interfaces();
+                // The totals jump when interfaces come and go
+                let aggregate_changed = self.config.aggregate_interfaces
+                    && network_interfaces != self.network_interfaces;
+                self.network_interfaces = network_interfaces;
+                self.update_interface_counters_list();
+                self.select_preferred_network_interface();
+                self.update_popup_tabs();
+                if aggregate_changed {
+                    self.reset_byte_counters();
+                }
+                let mut tasks = vec![self.update_bssid()];
+                if let (Some(from), Some(to)) =
+                    (previous_interface.as_ref(), self.get_monitored_interface())
+                    && *from != to
+                {
+                    if self.config.notify_interface_change {
+                        notification::notify(
+                            fl!(
+                                "interface-changed",
+                                interface = self.get_interface_display_name(&to)
+                            ),
+                            format!("{from} → {to}"),
+                        );
                     }
-                } else {
-                    self.network_interfaces = network::get_network_interfaces();
-                    self.select_default_network_interface();
+                    tasks.push(self.flash_interface_label());
+                }
+                self.emit_interface_changed(previous_interface);
+                self.update_interface_label();
+                self.update_link_local_only();
+                if self.popup.is_some() {
+                    tasks.push(self.update_popup_details());
+                }
+                return cosmic::Task::batch(tasks);
+            }
+            Message::UpdateSelectedNetworkInterface(option) => {
+                let previous_interface = self.selected_network_interface_name();
+                // The first option selects an interface automatically
+                let network_interface = option
+                    .checked_sub(1)
+                    .and_then(|index| self.network_interfaces.get(index))
+                    .cloned();
+                self.scrolled_interface = None;
+                self.config
+                    .set_network_interface(&self.config_helper, network_interface)
+                    .unwrap();
+                self.select_preferred_network_interface();
+                self.emit_interface_changed(previous_interface);
+                self.update_interface_label();
+                self.update_link_local_only();
+                return cosmic::Task::batch([self.update_bssid(), self.update_popup_details()]);
+            }
+            Message::Scrolled(delta) => {
+                self.scroll_offset += match delta {
+                    iced::mouse::ScrollDelta::Lines { y, .. } => y,
+                    iced::mouse::ScrollDelta::Pixels { y, .. } => y / SCROLL_STEP_PIXELS,
+                };
+                let steps = self.scroll_offset.trunc();
+                if steps == 0.0 || self.network_interfaces.is_empty() {
+                    return cosmic::Task::none();
                 }
+                self.scroll_offset -= steps;
+                // Scrolling down moves to the next interface
+                let count = self.network_interfaces.len() as i64;
+                let current = self.selected_network_interface.unwrap_or(0) as i64;
+                let next = (current - steps as i64).rem_euclid(count) as usize;
+                let previous_interface = self.selected_network_interface_name();
+                self.scrolled_interface = self.network_interfaces.get(next).cloned();
+                self.select_preferred_network_interface();
+                self.emit_interface_changed(previous_interface);
+                self.update_interface_label();
+                self.update_link_local_only();
+                return cosmic::Task::batch([
+                    self.flash_interface_label(),
+                    self.update_bssid(),
+                    self.update_popup_details(),
+                ]);
             }
-            Message::UpdateSelectedNetworkInterface(new_interface) => {
-                self.selected_network_interface = Some(new_interface);
-                let interface = self.network_interfaces.get(0).unwrap();
-                self.received_bytes = network::get_received_bytes(interface).unwrap_or(0);
-                self.sent_bytes = network::get_sent_bytes(interface).unwrap_or(0);
+            Message::EndLabelFlash => {
+                self.label_flashes = self.label_flashes.saturating_sub(1);
+                self.update_interface_label();
             }
             Message::UnitChanged(entity) => {
                 if !self.unit_model.is_active(entity) {
                     self.unit_model.activate(entity);
+                    // The upload unit follows until it is chosen separately
+                    let upload_follows = self.config.upload_unit.is_none();
                     if entity == self.bits_entity {
-                        self.download_speed *= 8;
-                        self.upload_speed *= 8;
+                        if upload_follows {
+                            self.upload_unit_model.activate(self.upload_bits_entity);
+                        }
                         self.config
                             .set_unit(&self.config_helper, Unit::Bits)
                             .unwrap();
                     } else if entity == self.bytes_entity {
-                        self.download_speed /= 8;
-                        self.upload_speed /= 8;
+                        if upload_follows {
+                            self.upload_unit_model.activate(self.upload_bytes_entity);
+                        }
                         self.config
                             .set_unit(&self.config_helper, Unit::Bytes)
                             .unwrap();
+                    } else if entity == self.packets_entity {
+                        if upload_follows {
+                            self.upload_unit_model.activate(self.upload_packets_entity);
+                        }
+                        self.config
+                            .set_unit(&self.config_helper, Unit::Packets)
+                            .unwrap();
                     }
                     self.set_download_speed_display();
                     self.set_upload_speed_display();
+                    self.set_panel_interfaces_display();
                 }
             }
+            Message::UploadUnitChanged(entity) => {
+                if !self.upload_unit_model.is_active(entity) {
+                    self.upload_unit_model.activate(entity);
+                    if entity == self.upload_bits_entity {
+                        self.config
+                            .set_upload_unit(&self.config_helper, Some(Unit::Bits))
+                            .unwrap();
+                    } else if entity == self.upload_bytes_entity {
+                        self.config
+                            .set_upload_unit(&self.config_helper, Some(Unit::Bytes))
+                            .unwrap();
+                    } else if entity == self.upload_packets_entity {
+                        self.config
+                            .set_upload_unit(&self.config_helper, Some(Unit::Packets))
+                            .unwrap();
+                    }
+                    self.set_upload_speed_display();
+                    self.set_panel_interfaces_display();
+                }
+            }
+            Message::ShowBothUnitsChanged(show) => {
+                self.config
+                    .set_show_both_units(&self.config_helper, show)
+                    .unwrap();
+                self.set_download_speed_display();
+                self.set_upload_speed_display();
+                self.set_panel_interfaces_display();
+            }
+            Message::UnitScaleChanged(index) => {
+                let scale = match index {
+                    1 => UnitScale::Si,
+                    _ => UnitScale::Iec,
+                };
+                self.config
+                    .set_unit_scale(&self.config_helper, scale)
+                    .unwrap();
+                self.set_download_speed_display();
+                self.set_upload_speed_display();
+                self.set_panel_interfaces_display();
+            }
+            Message::IecLabelsChanged(iec_labels) => {
+                self.config
+                    .set_iec_labels(&self.config_helper, iec_labels)
+                    .unwrap();
+                self.set_download_speed_display();
+                self.set_upload_speed_display();
+                self.update_text_metrics(&self.interface_font.clone());
+            }
+            Message::FixedPrefixChanged(index) => {
+                let prefix = match index {
+                    1 => Some(UnitPrefix::None),
+                    2 => Some(UnitPrefix::Kilo),
+                    3 => Some(UnitPrefix::Mega),
+                    4 => Some(UnitPrefix::Giga),
+                    5 => Some(UnitPrefix::Tera),
+                    _ => None,
+                };
+                self.config
+                    .set_fixed_prefix(&self.config_helper, prefix)
+                    .unwrap();
+                // Values under a fixed prefix take more digits
+                self.update_text_metrics(&self.interface_font.clone());
+                self.set_download_speed_display();
+                self.set_upload_speed_display();
+                self.set_panel_interfaces_display();
+            }
+            Message::DisplayModeChanged(index) => {
+                let mode = match index {
+                    1 => DisplayMode::Total,
+                    2 => DisplayMode::Today,
+                    _ => DisplayMode::Rate,
+                };
+                self.config
+                    .set_display_mode(&self.config_helper, mode)
+                    .unwrap();
+                self.set_download_speed_display();
+                self.set_upload_speed_display();
+                self.set_panel_interfaces_display();
+            }
+            Message::ResetSessionTotals => {
+                self.monitored.session_totals = SessionTotals::default();
+                self.session_peaks = (None, None);
+                for panel_interface in &mut self.panel_interfaces {
+                    panel_interface.counters.session_totals = SessionTotals::default();
+                }
+                for (_, counters) in &mut self.interface_counters {
+                    counters.session_totals = SessionTotals::default();
+                }
+                self.set_download_speed_display();
+                self.set_upload_speed_display();
+                self.set_panel_interfaces_display();
+            }
             Message::UpdateRateChanged(rate) => {
                 self.config
-                    .set_update_rate(&self.config_helper, rate)
+                    .set_update_interval_ms(&self.config_helper, rate)
+                    .unwrap();
+            }
+            Message::SmoothingChanged(smoothing) => {
+                self.config
+                    .set_smoothing(&self.config_helper, smoothing)
+                    .unwrap();
+            }
+            Message::ShowPeakChanged(show) => {
+                self.config
+                    .set_show_peak(&self.config_helper, show)
+                    .unwrap();
+            }
+            Message::PeakWindowChanged(window) => {
+                self.config
+                    .set_peak_window(&self.config_helper, window)
+                    .unwrap();
+            }
+            Message::ShowSparklineChanged(show) => {
+                self.config
+                    .set_show_sparkline(&self.config_helper, show)
+                    .unwrap();
+            }
+            Message::SparklineLengthChanged(length) => {
+                self.config
+                    .set_sparkline_length(&self.config_helper, length)
+                    .unwrap();
+            }
+            Message::GraphLogScaleChanged(log_scale) => {
+                self.config
+                    .set_graph_log_scale(&self.config_helper, log_scale)
+                    .unwrap();
+            }
+            Message::GraphStyleChanged(index) => {
+                let style = match index {
+                    1 => GraphStyle::Stacked,
+                    _ => GraphStyle::Lines,
+                };
+                self.config
+                    .set_graph_style(&self.config_helper, style)
+                    .unwrap();
+            }
+            Message::HistoryRetentionChanged(index) => {
+                self.config
+                    .set_history_retention(&self.config_helper, HISTORY_RETENTIONS[index])
+                    .unwrap();
+            }
+            Message::UsageRetentionChanged(days) => {
+                self.config
+                    .set_usage_retention(&self.config_helper, days)
+                    .unwrap();
+            }
+            Message::DataCapChanged(data_cap) => {
+                self.config
+                    .set_data_cap(&self.config_helper, data_cap)
+                    .unwrap();
+            }
+            Message::DataCapThresholdsInput(input) => {
+                let thresholds = input
+                    .split(',')
+                    .filter_map(|threshold| threshold.trim().parse().ok())
+                    .collect();
+                self.data_cap_thresholds_input = input;
+                self.config
+                    .set_data_cap_thresholds(&self.config_helper, thresholds)
+                    .unwrap();
+            }
+            Message::BillingDayChanged(day) => {
+                self.config
+                    .set_billing_day(&self.config_helper, day)
+                    .unwrap();
+            }
+            Message::MinimumRateChanged(rate) => {
+                self.config
+                    .set_minimum_rate(&self.config_helper, rate)
+                    .unwrap();
+            }
+            Message::HideBelowMinimumChanged(hide) => {
+                self.config
+                    .set_hide_below_minimum(&self.config_helper, hide)
+                    .unwrap();
+            }
+            Message::TabularDigitsChanged(tabular_digits) => {
+                self.config
+                    .set_tabular_digits(&self.config_helper, tabular_digits)
+                    .unwrap();
+            }
+            Message::UploadFirstChanged(upload_first) => {
+                self.config
+                    .set_upload_first(&self.config_helper, upload_first)
                     .unwrap();
             }
+            Message::CompactChanged(compact) => {
+                self.config
+                    .set_compact(&self.config_helper, compact)
+                    .unwrap();
+            }
+            Message::ShowUnitChanged(show) => {
+                self.config
+                    .set_show_unit(&self.config_helper, show)
+                    .unwrap();
+            }
+            Message::DownloadSymbolChanged(symbol) => {
+                self.config
+                    .set_download_symbol(&self.config_helper, symbol)
+                    .unwrap();
+                self.set_download_speed_display();
+                self.update_text_metrics(&self.interface_font.clone());
+            }
+            Message::UploadSymbolChanged(symbol) => {
+                self.config
+                    .set_upload_symbol(&self.config_helper, symbol)
+                    .unwrap();
+                self.set_upload_speed_display();
+                self.update_text_metrics(&self.interface_font.clone());
+            }
+            Message::SymbolBeforeValueChanged(before) => {
+                self.config
+                    .set_symbol_before_value(&self.config_helper, before)
+                    .unwrap();
+                self.set_download_speed_display();
+                self.set_upload_speed_display();
+                self.update_text_metrics(&self.interface_font.clone());
+            }
             Message::ShowDownloadSpeedChanged(show) => {
                 self.config
                     .set_show_download_speed(&self.config_helper, show)
@@ -662,6 +3946,351 @@ impl cosmic::Application for AppModel {
                     .set_show_upload_speed(&self.config_helper, show)
                     .unwrap();
             }
+            Message::ShowReachabilityChanged(show) => {
+                self.config
+                    .set_show_reachability(&self.config_helper, show)
+                    .unwrap();
+                self.online = None;
+                if show {
+                    return cosmic::task::message(cosmic::Action::App(Message::ProbeReachability));
+                }
+            }
+            Message::AggregateInterfacesChanged(aggregate) => {
+                self.config
+                    .set_aggregate_interfaces(&self.config_helper, aggregate)
+                    .unwrap();
+                self.reset_byte_counters();
+                self.update_interface_label();
+            }
+            Message::IncludeVirtualInterfacesChanged(include) => {
+                self.config
+                    .set_include_virtual_interfaces(&self.config_helper, include)
+                    .unwrap();
+                return cosmic::task::message(cosmic::Action::App(
+                    Message::UpdateNetworkInterfaces,
+                ));
+            }
+            Message::PreferVpnChanged(prefer) => {
+                self.config
+                    .set_prefer_vpn(&self.config_helper, prefer)
+                    .unwrap();
+                return cosmic::task::message(cosmic::Action::App(
+                    Message::UpdateNetworkInterfaces,
+                ));
+            }
+            Message::LinkPreferenceChanged(index) => {
+                let preference = match index {
+                    1 => LinkPreference::Wired,
+                    2 => LinkPreference::Wireless,
+                    _ => LinkPreference::Any,
+                };
+                self.config
+                    .set_link_preference(&self.config_helper, preference)
+                    .unwrap();
+                return cosmic::task::message(cosmic::Action::App(
+                    Message::UpdateNetworkInterfaces,
+                ));
+            }
+            Message::ExclusionPatternInput(pattern) => {
+                self.exclusion_pattern = pattern;
+            }
+            Message::AddExclusionPattern => {
+                let pattern = std::mem::take(&mut self.exclusion_pattern);
+                let mut excluded_interfaces = self.config.excluded_interfaces.clone();
+                excluded_interfaces.push(pattern.trim().to_string());
+                self.config
+                    .set_excluded_interfaces(&self.config_helper, excluded_interfaces)
+                    .unwrap();
+                return cosmic::task::message(cosmic::Action::App(
+                    Message::UpdateNetworkInterfaces,
+                ));
+            }
+            Message::RemoveExclusionPattern(index) => {
+                let mut excluded_interfaces = self.config.excluded_interfaces.clone();
+                if index < excluded_interfaces.len() {
+                    excluded_interfaces.remove(index);
+                    self.config
+                        .set_excluded_interfaces(&self.config_helper, excluded_interfaces)
+                        .unwrap();
+                    return cosmic::task::message(cosmic::Action::App(
+                        Message::UpdateNetworkInterfaces,
+                    ));
+                }
+            }
+            Message::InterfaceLabelChanged(index) => {
+                let label = match index {
+                    1 => InterfaceLabel::Name,
+                    2 => InterfaceLabel::Abbreviation,
+                    _ => InterfaceLabel::None,
+                };
+                self.config
+                    .set_interface_label(&self.config_helper, label)
+                    .unwrap();
+                self.update_interface_label();
+                self.update_panel_interfaces();
+            }
+            Message::InterfaceColoringChanged(index) => {
+                let coloring = match index {
+                    1 => InterfaceColoring::Text,
+                    2 => InterfaceColoring::Dot,
+                    _ => InterfaceColoring::None,
+                };
+                self.config
+                    .set_interface_coloring(&self.config_helper, coloring)
+                    .unwrap();
+            }
+            Message::PanelInterfaceToggled(network_interface, show) => {
+                let mut panel_interfaces = self.config.panel_interfaces.clone();
+                panel_interfaces.retain(|name| *name != network_interface);
+                if show {
+                    panel_interfaces.push(network_interface);
+                }
+                self.config
+                    .set_panel_interfaces(&self.config_helper, panel_interfaces)
+                    .unwrap();
+                self.update_panel_interfaces();
+            }
+            Message::ReachabilityTargetChanged(target) => {
+                self.config
+                    .set_reachability_target(&self.config_helper, target)
+                    .unwrap();
+            }
+            Message::StatsBackendChanged(index) => {
+                let backend = match index {
+                    1 => StatsBackend::Sysfs,
+                    2 => StatsBackend::ProcNetDev,
+                    _ => StatsBackend::Netlink,
+                };
+                self.config
+                    .set_stats_backend(&self.config_helper, backend)
+                    .unwrap();
+                self.stats = stats::get_stats_source(backend);
+                self.stats.refresh();
+                self.reset_byte_counters();
+            }
+            Message::EventCommandChanged(command) => {
+                self.config
+                    .set_event_command(&self.config_helper, command)
+                    .unwrap();
+            }
+            Message::WebhookUrlChanged(url) => {
+                self.config
+                    .set_webhook_url(&self.config_helper, url)
+                    .unwrap();
+            }
+            Message::WakeOnLanMacChanged(mac_address) => {
+                self.config
+                    .set_wake_on_lan_mac(&self.config_helper, mac_address)
+                    .unwrap();
+            }
+            Message::InterfaceAliasChanged(network_interface, alias) => {
+                let mut interface_aliases = self.config.interface_aliases.clone();
+                if alias.is_empty() {
+                    interface_aliases.remove(&network_interface);
+                } else {
+                    interface_aliases.insert(network_interface, alias);
+                }
+                self.config
+                    .set_interface_aliases(&self.config_helper, interface_aliases)
+                    .unwrap();
+                self.update_interface_label();
+                self.update_popup_tabs();
+            }
+            Message::PaletteChanged(index) => {
+                let palette = match index {
+                    1 => Palette::RedGreenSafe,
+                    2 => Palette::BlueYellowSafe,
+                    _ => Palette::Theme,
+                };
+                self.config
+                    .set_palette(&self.config_helper, palette)
+                    .unwrap();
+            }
+            Message::Announce => {
+                let network_interface = self.get_monitored_interface();
+                let download_unit = self
+                    .config
+                    .get_download_unit_for(network_interface.as_deref());
+                let upload_unit = self
+                    .config
+                    .get_upload_unit_for(network_interface.as_deref());
+                speech::announce(fl!(
+                    "announcement",
+                    download = self.get_spoken_speed(
+                        download_unit.convert(
+                            self.monitored.download_speed,
+                            self.monitored.download_packet_speed
+                        ),
+                        download_unit
+                    ),
+                    upload = self.get_spoken_speed(
+                        upload_unit.convert(
+                            self.monitored.upload_speed,
+                            self.monitored.upload_packet_speed
+                        ),
+                        upload_unit
+                    )
+                ));
+            }
+            Message::ProbeReachability => {
+                return cosmic::Task::perform(
+                    reachability::probe(self.config.reachability_target.clone()),
+                    |online| cosmic::Action::App(Message::ReachabilityUpdated(online)),
+                );
+            }
+            Message::ReachabilityUpdated(online) => {
+                self.online = Some(online);
+            }
+            Message::WakeOnLan => {
+                let Some(mac_address) = network::parse_mac_address(&self.config.wake_on_lan_mac)
+                else {
+                    eprintln!(
+                        "error while sending Wake-on-LAN packet: invalid MAC address {}",
+                        self.config.wake_on_lan_mac
+                    );
+                    return cosmic::Task::none();
+                };
+                let network_interface = self.selected_network_interface_name();
+                if let Err(why) =
+                    network::send_magic_packet(mac_address, network_interface.as_deref())
+                {
+                    eprintln!("error while sending Wake-on-LAN packet: {why}");
+                }
+            }
+            Message::DevicesUpdated(devices) => {
+                self.devices = devices;
+            }
+            Message::ConnectionNameUpdated(name) => {
+                self.connection_name = name;
+            }
+            Message::GatewayUpdated(gateway) => {
+                self.gateway = gateway;
+            }
+            Message::BssidUpdated(network_interface, bssid) => {
+                let previous = self.bssid.take();
+                if let (Some((previous_interface, from)), Some(to)) = (previous, &bssid)
+                    && previous_interface == network_interface
+                    && &from != to
+                {
+                    self.roaming_log.insert(
+                        0,
+                        format!("{}  {from} → {to}", chrono::Local::now().format("%H:%M")),
+                    );
+                    self.roaming_log.truncate(ROAMING_LOG_LENGTH);
+                    if self.config.notify_roaming {
+                        notification::notify(
+                            fl!("roamed", interface = network_interface.clone()),
+                            format!("{from} → {to}"),
+                        );
+                    }
+                    self.emit(Event::Roamed {
+                        network_interface: network_interface.clone(),
+                        from,
+                        to: to.clone(),
+                    });
+                }
+                self.bssid = bssid.map(|bssid| (network_interface, bssid));
+            }
+            Message::SetDeviceConnected(device, connected) => {
+                return cosmic::Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            if let Err(why) = nm::set_device_connected(&device, connected) {
+                                eprintln!("error while changing device state: {why}");
+                            }
+                            nm::get_devices()
+                        })
+                        .await
+                        .unwrap_or_default()
+                    },
+                    |devices| cosmic::Action::App(Message::DevicesUpdated(devices)),
+                );
+            }
+            Message::FirewallStatusUpdated(status) => {
+                self.firewall_status = status;
+            }
+            Message::ShapingLimitUpdated(limit) => {
+                self.shaping_limit = limit;
+            }
+            Message::ShapingRateChanged(rate) => {
+                self.config
+                    .set_shaping_rate(&self.config_helper, rate)
+                    .unwrap();
+            }
+            Message::SetShapingLimit(rate) => {
+                let Some(network_interface) = self.selected_network_interface_name() else {
+                    return cosmic::Task::none();
+                };
+                self.applying_shaping_limit = true;
+                return cosmic::Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            if let Err(why) = shaping::set_shaping_limit(&network_interface, rate) {
+                                eprintln!("error while changing shaping limit: {why}");
+                            }
+                        })
+                        .await
+                    },
+                    |_| cosmic::Action::App(Message::ShapingLimitSet),
+                );
+            }
+            Message::ShapingLimitSet => {
+                self.applying_shaping_limit = false;
+                return self.update_popup_details();
+            }
+            Message::ListeningPortsUpdated(ports) => {
+                self.listening_ports = ports;
+            }
+            Message::ToggleListeningPorts => {
+                self.show_listening_ports = !self.show_listening_ports;
+            }
+            Message::Reconnect => {
+                let Some(network_interface) = self.selected_network_interface_name() else {
+                    return cosmic::Task::none();
+                };
+                self.reconnecting = true;
+                return cosmic::Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            if let Err(why) = nm::reconnect(&network_interface) {
+                                eprintln!("error while reconnecting: {why}");
+                            }
+                        })
+                        .await
+                    },
+                    |_| cosmic::Action::App(Message::Reconnected),
+                );
+            }
+            Message::Reconnected => {
+                self.reconnecting = false;
+                return self.update_popup_details();
+            }
+            Message::PollPlugins => {
+                if self.data_sources.is_empty() {
+                    self.plugin_readings.clear();
+                    return cosmic::Task::none();
+                }
+                // Skipped while a slow command still runs
+                if self.polling_plugins {
+                    return cosmic::Task::none();
+                }
+                self.polling_plugins = true;
+                let data_sources = self.data_sources.clone();
+                return cosmic::Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            plugin::poll_data_sources(&data_sources)
+                        })
+                        .await
+                        .unwrap_or_default()
+                    },
+                    |readings| cosmic::Action::App(Message::PluginsPolled(readings)),
+                );
+            }
+            Message::PluginsPolled(readings) => {
+                self.polling_plugins = false;
+                self.plugin_readings = readings;
+            }
             Message::Rectangle(u) => match u {
                 RectangleUpdate::Rectangle(r) => {
                     self.rectangle = r.1;
@@ -671,7 +4300,59 @@ impl cosmic::Application for AppModel {
                 }
             },
             Message::UpdateConfig(config) => {
+                let plugins_changed = config.plugins != self.config.plugins;
+                let probe_changed = config.show_reachability
+                    && (!self.config.show_reachability
+                        || config.reachability_target != self.config.reachability_target);
+                let exclusions_changed = config.excluded_interfaces
+                    != self.config.excluded_interfaces
+                    || config.include_virtual_interfaces != self.config.include_virtual_interfaces
+                    || config.prefer_vpn != self.config.prefer_vpn;
+                let backend_changed = config.stats_backend != self.config.stats_backend;
+                let selection_changed = config.aggregate_interfaces
+                    != self.config.aggregate_interfaces
+                    || config.network_interface != self.config.network_interface
+                    || config.interface_priority != self.config.interface_priority
+                    || config.link_preference != self.config.link_preference;
                 self.config = config;
+                self.history.set_retention(self.config.history_retention);
+                if backend_changed {
+                    self.stats = stats::get_stats_source(self.config.stats_backend);
+                    self.stats.refresh();
+                    self.reset_byte_counters();
+                }
+                if selection_changed {
+                    let previous_interface = self.selected_network_interface_name();
+                    self.scrolled_interface = None;
+                    self.select_preferred_network_interface();
+                    self.reset_byte_counters();
+                    self.emit_interface_changed(previous_interface);
+                    self.update_link_local_only();
+                }
+                self.update_interface_label();
+                self.update_panel_interfaces();
+                self.update_popup_tabs();
+                let mut tasks = Vec::new();
+                if plugins_changed {
+                    self.data_sources = plugin::get_data_sources(&self.config.plugins);
+                }
+                if plugins_changed && self.popup.is_some() {
+                    tasks.push(cosmic::task::message(cosmic::Action::App(
+                        Message::PollPlugins,
+                    )));
+                }
+                if probe_changed {
+                    self.online = None;
+                    tasks.push(cosmic::task::message(cosmic::Action::App(
+                        Message::ProbeReachability,
+                    )));
+                }
+                if exclusions_changed || backend_changed {
+                    tasks.push(cosmic::task::message(cosmic::Action::App(
+                        Message::UpdateNetworkInterfaces,
+                    )));
+                }
+                return cosmic::Task::batch(tasks);
             }
             Message::TogglePopup => {
                 return if let Some(p) = self.popup.take() {
@@ -679,6 +4360,11 @@ impl cosmic::Application for AppModel {
                 } else {
                     let new_id = window::Id::unique();
                     self.popup.replace(new_id);
+                    let tab = match self.selected_network_interface_name() {
+                        Some(network_interface) => PopupTab::Interface(network_interface),
+                        None => PopupTab::Overview,
+                    };
+                    self.activate_popup_tab(&tab);
                     let mut popup_settings = self.core.applet.get_popup_settings(
                         self.core().main_window_id().unwrap(),
                         new_id,
@@ -698,19 +4384,21 @@ impl cosmic::Application for AppModel {
                         width: width.max(1.) as i32,
                         height: height.max(1.) as i32,
                     };
-                    get_popup(popup_settings)
+                    cosmic::Task::batch([
+                        get_popup(popup_settings),
+                        self.update_popup_details(),
+                        cosmic::task::message(cosmic::Action::App(Message::PollPlugins)),
+                    ])
                 };
             }
             Message::ThemeChanged(theme) => {
-                self.data_width = self
-                    .get_text_width_and_height("00.00", &theme.interface_font)
-                    .0;
-                self.unit_width = self
-                    .get_text_width_and_height("Mb/s  ↓", &theme.interface_font)
-                    .0;
-                self.line_height = self
-                    .get_text_width_and_height("1234567890.KM/Bb↓↑", &theme.interface_font)
-                    .1;
+                self.update_text_metrics(&theme.interface_font);
+            }
+            Message::PopupTabActivated(entity) => {
+                self.popup_tabs.activate(entity);
+                if self.popup_tabs.active_data::<PopupTab>() == Some(&PopupTab::Usage) {
+                    self.read_heatmap();
+                }
             }
             Message::PopupClosed(id) => {
                 self.popup.take_if(|stored_id| stored_id == &id);
@@ -728,3 +4416,134 @@ impl cosmic::Application for AppModel {
         Some(cosmic::applet::style())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::stats::Mock, std::time::Duration};
+
+    /// Counters of an interface receiving `bytes` and sending a fifth of
+    /// it, in packets of 1000 bytes
+    fn traffic(bytes: u64) -> Counters {
+        Counters {
+            received_bytes: bytes,
+            sent_bytes: bytes / 5,
+            received_packets: bytes / 1000,
+            sent_packets: bytes / 5000,
+        }
+    }
+
+    #[test]
+    fn update_counts_the_traffic_since_the_last_reading() {
+        let mut mock = Mock::new([("mock0", traffic(1_000_000))]);
+        let mut counters = InterfaceCounters::new(&mock, "mock0");
+        mock.refresh();
+        assert_eq!(counters.update(&mock, "mock0", 1), (1_000_000, 200_000));
+        mock.refresh();
+        assert_eq!(counters.update(&mock, "mock0", 1), (1_000_000, 200_000));
+        assert_eq!(counters.session_totals.received_bytes, 2_000_000);
+        assert_eq!(counters.session_totals.sent_bytes, 400_000);
+        assert_eq!(counters.session_totals.received_packets, 2000);
+        assert_eq!(counters.session_totals.sent_packets, 400);
+        assert_eq!(counters.since_boot, (2_000_000, 400_000));
+        assert_eq!(counters.usage.today().received_bytes, 2_000_000);
+    }
+
+    #[test]
+    fn update_rebaselines_reset_counters() {
+        let mut mock = Mock::new([("mock0", traffic(1_000_000))]);
+        mock.refresh();
+        let mut counters = InterfaceCounters::new(&mock, "mock0");
+        mock.set("mock0", traffic(0));
+        assert_eq!(counters.update(&mock, "mock0", 1), (0, 0));
+        mock.refresh();
+        assert_eq!(counters.update(&mock, "mock0", 1), (1_000_000, 200_000));
+    }
+
+    #[test]
+    fn update_counts_wrapped_32_bit_counters() {
+        let limit = 1 << 32;
+        let mut mock = Mock::new([("mock0", traffic(1_000_000))]).with_limit(limit);
+        mock.set("mock0", traffic(limit - 500_000));
+        let mut counters = InterfaceCounters::new(&mock, "mock0");
+        mock.refresh();
+        assert_eq!(mock.counters("mock0").unwrap().received_bytes, 500_000);
+        assert_eq!(counters.update(&mock, "mock0", 1).0, 1_000_000);
+    }
+
+    #[test]
+    fn speeds_are_measured_over_the_elapsed_time() {
+        let mut counters = InterfaceCounters::from_reading(None, Some(traffic(0)));
+        let read_at = counters.read_at + Duration::from_secs(2);
+        counters.add_reading(Some(traffic(1_000_000)), None, read_at, 1);
+        assert_eq!(counters.download_speed, 500_000);
+        assert_eq!(counters.upload_speed, 100_000);
+        assert_eq!(counters.download_packet_speed, 500);
+        assert_eq!(counters.upload_packet_speed, 100);
+    }
+
+    #[test]
+    fn speeds_are_averaged_over_the_smoothing() {
+        let mut counters = InterfaceCounters::from_reading(None, Some(traffic(0)));
+        let start = counters.read_at;
+        counters.add_reading(
+            Some(traffic(1_000_000)),
+            None,
+            start + Duration::from_secs(1),
+            2,
+        );
+        counters.add_reading(
+            Some(traffic(4_000_000)),
+            None,
+            start + Duration::from_secs(2),
+            2,
+        );
+        assert_eq!(counters.download_speed, 2_000_000);
+        counters.add_reading(
+            Some(traffic(4_000_000)),
+            None,
+            start + Duration::from_secs(3),
+            2,
+        );
+        assert_eq!(counters.download_speed, 1_500_000);
+    }
+
+    #[test]
+    fn interfaces_coming_back_count_nothing_at_first() {
+        let mut counters = InterfaceCounters::from_reading(None, Some(traffic(0)));
+        let start = counters.read_at;
+        assert_eq!(
+            counters.add_reading(None, None, start + Duration::from_secs(1), 1),
+            (0, 0)
+        );
+        assert_eq!(
+            counters.add_reading(
+                Some(traffic(1_000_000)),
+                None,
+                start + Duration::from_secs(2),
+                1
+            ),
+            (0, 0)
+        );
+        assert_eq!(
+            counters.add_reading(
+                Some(traffic(2_000_000)),
+                None,
+                start + Duration::from_secs(3),
+                1
+            ),
+            (1_000_000, 200_000)
+        );
+    }
+
+    #[test]
+    fn reset_keeps_the_totals() {
+        let mut counters = InterfaceCounters::from_reading(None, Some(traffic(0)));
+        let read_at = counters.read_at + Duration::from_secs(1);
+        counters.add_reading(Some(traffic(1_000_000)), None, read_at, 3);
+        counters.reset(Some(traffic(5_000_000)));
+        assert!(counters.download_samples.is_empty());
+        assert_eq!(counters.received_bytes, Some(5_000_000));
+        assert_eq!(counters.session_totals.received_bytes, 1_000_000);
+        assert_eq!(counters.since_boot.0, 1_000_000);
+    }
+}
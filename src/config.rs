@@ -1,32 +1,468 @@
 use {
-    cosmic::cosmic_config::{self, CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry},
+    cosmic::cosmic_config::{
+        self, ConfigGet, CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry,
+    },
     serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, time::Duration},
 };
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+/// Shortest time between two updates, in milliseconds
+pub const MIN_UPDATE_RATE: u32 = 250;
+
+/// Longest time between two updates, in milliseconds
+pub const MAX_UPDATE_RATE: u32 = 10_000;
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Unit {
     Bits,
     #[default]
     Bytes,
+    Packets,
+}
+
+impl Unit {
+    /// Expresses traffic counted in bytes and packets in this unit.
+    pub fn convert(self, bytes: u64, packets: u64) -> u64 {
+        match self {
+            Unit::Bits => bytes * 8,
+            Unit::Bytes => bytes,
+            Unit::Packets => packets,
+        }
+    }
+
+    /// Unit shown next to this one when both bits and bytes are shown
+    pub fn counterpart(self) -> Unit {
+        match self {
+            Unit::Bits => Unit::Bytes,
+            Unit::Bytes | Unit::Packets => Unit::Bits,
+        }
+    }
+}
+
+/// Multiple between two unit prefixes
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitScale {
+    /// Powers of 1024
+    #[default]
+    Iec,
+    /// Powers of 1000, as reported by ISPs and browsers
+    Si,
+}
+
+impl UnitScale {
+    pub fn base(self) -> u64 {
+        match self {
+            UnitScale::Iec => 1024,
+            UnitScale::Si => 1000,
+        }
+    }
+
+    /// Number of prefixes `value` reaches, e.g. 2 from a mega onwards.
+    pub fn exponent(self, value: u64) -> u32 {
+        if value > 0 {
+            value.ilog(self.base())
+        } else {
+            0
+        }
+    }
+}
+
+/// What the panel counts
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    #[default]
+    Rate,
+    /// Traffic since the applet started or the totals were reset
+    Total,
+    /// Traffic since midnight
+    Today,
+}
+
+/// Prefix the rates can be locked to
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitPrefix {
+    None,
+    Kilo,
+    Mega,
+    Giga,
+    Tera,
+}
+
+impl UnitPrefix {
+    /// Power of the unit scale the prefix stands for
+    pub fn exponent(self) -> u32 {
+        self as u32
+    }
+}
+
+/// How the monitored interface is labelled in the panel
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterfaceLabel {
+    #[default]
+    None,
+    Name,
+    Abbreviation,
+}
+
+/// How the panel marks the interface its rates belong to
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterfaceColoring {
+    #[default]
+    None,
+    /// Tint the label and rates
+    Text,
+    /// Show a dot before the label
+    Dot,
+}
+
+/// How the popup graph draws the download and upload speeds
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphStyle {
+    #[default]
+    Lines,
+    /// Upload area on top of the download area, showing the total
+    Stacked,
+}
+
+/// Kind of link picked first when several interfaces are up
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkPreference {
+    #[default]
+    Any,
+    Wired,
+    Wireless,
+}
+
+/// Where the interfaces and their counters are read from
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsBackend {
+    /// One `RTM_GETLINK` request per update, `/sys/class/net` where netlink
+    /// is not available
+    #[default]
+    Netlink,
+    /// `/sys/class/net`
+    Sysfs,
+    /// `/proc/net/dev`, read once per update for every interface
+    #[serde(rename = "proc-net-dev")]
+    ProcNetDev,
+}
+
+/// Colors of graphs and status indicators
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    #[default]
+    Theme,
+    /// For deuteranopia and protanopia
+    RedGreenSafe,
+    /// For tritanopia
+    BlueYellowSafe,
+}
+
+/// Events that can trigger the user's hook command or webhook
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    InterfaceChanged,
+    Roamed,
+    DataCapCrossed,
+}
+
+/// Settings of one interface taking precedence over the global ones.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+#[serde(default)]
+pub struct InterfaceSettings {
+    /// Unit of both speeds
+    pub unit: Option<Unit>,
+    pub show_download_speed: Option<bool>,
+    pub show_upload_speed: Option<bool>,
+    /// Color of the interface in the panel as `#rrggbb`, replacing the one
+    /// picked from its name
+    pub color: Option<String>,
+}
+
+/// An external data source, see [`crate::plugin::ExternalSource`].
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct PluginConfig {
+    pub name: String,
+    pub command: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, CosmicConfigEntry, Eq, PartialEq)]
 #[version = 1]
 pub struct BitrateAppletConfig {
+    /// Interface chosen in the popup, `None` to pick one automatically
+    pub network_interface: Option<String>,
+    /// Interfaces monitored in this order of preference when no interface is
+    /// chosen, the first one that is up wins
+    pub interface_priority: Vec<String>,
+    /// Whether the rates are summed over all active interfaces
+    pub aggregate_interfaces: bool,
+    /// Glob patterns of interfaces that are never monitored, e.g. `veth*`
+    pub excluded_interfaces: Vec<String>,
+    /// Whether bridges, tun/tap, veth and container interfaces are monitored
+    pub include_virtual_interfaces: bool,
+    /// Whether an active VPN tunnel is monitored instead of the physical interface
+    pub prefer_vpn: bool,
+    pub link_preference: LinkPreference,
+    /// Friendly names shown instead of interface names, e.g. `enp3s0f0` to
+    /// `Office LAN`
+    pub interface_aliases: BTreeMap<String, String>,
+    /// Settings overriding the global ones, keyed by interface name
+    pub interface_settings: BTreeMap<String, InterfaceSettings>,
+    /// Interfaces shown side by side in the panel, empty to show only the
+    /// monitored one
+    pub panel_interfaces: Vec<String>,
+    /// Unit of the download speed
     pub unit: Unit,
+    /// Unit of the upload speed, `None` to follow `unit`
+    pub upload_unit: Option<Unit>,
+    /// Whether every value is followed by its counterpart, bits after bytes
+    /// and bytes after bits
+    pub show_both_units: bool,
+    pub unit_scale: UnitScale,
+    /// Whether powers of 1024 are labelled Ki, Mi, Gi and Ti
+    pub iec_labels: bool,
+    pub display_mode: DisplayMode,
+    /// Prefix of every rate, `None` to pick the closest one
+    pub fixed_prefix: Option<UnitPrefix>,
+    pub stats_backend: StatsBackend,
+    /// Seconds between two updates, as set before `update_interval_ms`
+    /// replaced it
     pub update_rate: u8,
+    /// Milliseconds between two updates, see [`Self::get_update_interval`]
+    pub update_interval_ms: u32,
+    /// Number of updates averaged into the shown rates, 1 to show them as measured
+    pub smoothing: u8,
+    /// Seconds over which the peak rates are kept
+    pub peak_window: u16,
+    /// Whether the peak rates are shown in the tooltip
+    pub show_peak: bool,
+    /// Whether a graph of the latest rates is shown in the panel
+    pub show_sparkline: bool,
+    /// Number of updates the panel graph spans
+    pub sparkline_length: u16,
+    /// Whether the popup graph has a logarithmic scale, so bursts do not
+    /// flatten the background traffic
+    pub graph_log_scale: bool,
+    pub graph_style: GraphStyle,
+    /// Seconds the measured rates are kept for
+    pub history_retention: u32,
+    /// Day of the month the monthly usage starts over on, the last day of
+    /// shorter months
+    pub billing_day: u8,
+    /// Monthly data cap in giga- or gibibytes following `unit_scale`, 0
+    /// without a cap
+    pub data_cap: u32,
+    /// Percentages of the data cap warned about once the monthly usage
+    /// crosses them
+    pub data_cap_thresholds: Vec<u8>,
+    /// Days the usage is kept for
+    pub usage_retention: u32,
+    /// Days the usage is kept per hour before it is only kept per day
+    pub hourly_usage_retention: u32,
+    /// Bytes per second under which a rate is shown as 0, to hide background chatter
+    pub minimum_rate: u32,
+    /// Whether rates under `minimum_rate` are left blank instead of shown as 0
+    pub hide_below_minimum: bool,
     pub show_download_speed: bool,
     pub show_upload_speed: bool,
+    /// Whether only the busier of download and upload is shown
+    pub compact: bool,
+    /// Whether the upload is shown before the download
+    pub upload_first: bool,
+    /// Whether values are padded to a fixed number of digits
+    pub tabular_digits: bool,
+    /// Whether the panel shows units after the values, they stay in the tooltip
+    pub show_unit: bool,
+    /// Symbols after the download and upload units, empty to hide them
+    pub download_symbol: String,
+    pub upload_symbol: String,
+    /// Whether the direction symbols come before the values rather than after the units
+    pub symbol_before_value: bool,
+    pub interface_label: InterfaceLabel,
+    pub interface_coloring: InterfaceColoring,
+    pub palette: Palette,
+    /// Seconds between two spoken announcements of the rates, 0 to disable
+    pub announce_interval: u16,
+    pub plugins: Vec<PluginConfig>,
+    /// Shell command run when one of `hooked_events` occurs, empty to disable
+    pub event_command: String,
+    pub hooked_events: Vec<EventKind>,
+    /// URL that receives a POST when one of `webhook_events` occurs, empty to disable
+    pub webhook_url: String,
+    /// JSON body with `${NAME}` placeholders, empty to send all event variables
+    pub webhook_template: String,
+    pub webhook_events: Vec<EventKind>,
+    /// Whether roaming between Wi-Fi access points shows a notification
+    pub notify_roaming: bool,
+    /// Whether switching to another interface automatically shows a notification
+    pub notify_interface_change: bool,
+    /// MAC address woken by the Wake-on-LAN action, empty to hide the action
+    pub wake_on_lan_mac: String,
+    /// Whether the popup offers to apply an egress limit with tc
+    pub show_shaping_control: bool,
+    /// Egress limit in Mbit/s applied from the popup
+    pub shaping_rate: u32,
+    /// Whether the panel shows an online/offline dot
+    pub show_reachability: bool,
+    /// Host or URL probed to determine reachability
+    pub reachability_target: String,
+    /// Seconds between two reachability probes
+    pub reachability_interval: u16,
 }
 
 impl Default for BitrateAppletConfig {
     fn default() -> Self {
         BitrateAppletConfig {
+            network_interface: None,
+            interface_priority: Vec::new(),
+            aggregate_interfaces: false,
+            excluded_interfaces: Vec::new(),
+            include_virtual_interfaces: false,
+            prefer_vpn: false,
+            link_preference: LinkPreference::Any,
+            interface_aliases: BTreeMap::new(),
+            interface_settings: BTreeMap::new(),
+            panel_interfaces: Vec::new(),
             unit: Unit::Bytes,
+            upload_unit: None,
+            show_both_units: false,
+            unit_scale: UnitScale::Iec,
+            iec_labels: false,
+            display_mode: DisplayMode::Rate,
+            fixed_prefix: None,
+            stats_backend: StatsBackend::default(),
             update_rate: 1,
+            update_interval_ms: 1000,
+            smoothing: 1,
+            peak_window: 60,
+            show_peak: false,
+            show_sparkline: false,
+            sparkline_length: 30,
+            graph_log_scale: false,
+            graph_style: GraphStyle::default(),
+            history_retention: 3600,
+            billing_day: 1,
+            data_cap: 0,
+            data_cap_thresholds: vec![80, 100],
+            usage_retention: 730,
+            hourly_usage_retention: 90,
+            minimum_rate: 0,
+            hide_below_minimum: false,
             show_download_speed: true,
             show_upload_speed: true,
+            compact: false,
+            upload_first: false,
+            tabular_digits: false,
+            show_unit: true,
+            download_symbol: String::from("↓"),
+            upload_symbol: String::from("↑"),
+            symbol_before_value: false,
+            interface_label: InterfaceLabel::None,
+            interface_coloring: InterfaceColoring::None,
+            palette: Palette::Theme,
+            announce_interval: 0,
+            plugins: Vec::new(),
+            event_command: String::new(),
+            hooked_events: vec![
+                EventKind::InterfaceChanged,
+                EventKind::Roamed,
+                EventKind::DataCapCrossed,
+            ],
+            webhook_url: String::new(),
+            webhook_template: String::new(),
+            webhook_events: vec![
+                EventKind::InterfaceChanged,
+                EventKind::Roamed,
+                EventKind::DataCapCrossed,
+            ],
+            notify_roaming: false,
+            notify_interface_change: false,
+            wake_on_lan_mac: String::new(),
+            show_shaping_control: false,
+            shaping_rate: 10,
+            show_reachability: false,
+            reachability_target: "1.1.1.1:443".to_string(),
+            reachability_interval: 30,
+        }
+    }
+}
+
+impl BitrateAppletConfig {
+    pub fn get_upload_unit(&self) -> Unit {
+        self.upload_unit.unwrap_or(self.unit)
+    }
+
+    fn get_interface_settings(
+        &self,
+        network_interface: Option<&str>,
+    ) -> Option<&InterfaceSettings> {
+        self.interface_settings.get(network_interface?)
+    }
+
+    /// Unit of the download speed of `network_interface`, `None` for traffic
+    /// that is not tied to a single interface
+    pub fn get_download_unit_for(&self, network_interface: Option<&str>) -> Unit {
+        self.get_interface_settings(network_interface)
+            .and_then(|settings| settings.unit)
+            .unwrap_or(self.unit)
+    }
+
+    pub fn get_upload_unit_for(&self, network_interface: Option<&str>) -> Unit {
+        self.get_interface_settings(network_interface)
+            .and_then(|settings| settings.unit)
+            .unwrap_or_else(|| self.get_upload_unit())
+    }
+
+    pub fn shows_download_speed_for(&self, network_interface: Option<&str>) -> bool {
+        self.get_interface_settings(network_interface)
+            .and_then(|settings| settings.show_download_speed)
+            .unwrap_or(self.show_download_speed)
+    }
+
+    pub fn shows_upload_speed_for(&self, network_interface: Option<&str>) -> bool {
+        self.get_interface_settings(network_interface)
+            .and_then(|settings| settings.show_upload_speed)
+            .unwrap_or(self.show_upload_speed)
+    }
+
+    /// Time between two updates, within [`MIN_UPDATE_RATE`] and
+    /// [`MAX_UPDATE_RATE`]
+    pub fn get_update_interval(&self) -> Duration {
+        Duration::from_millis(
+            self.update_interval_ms
+                .clamp(MIN_UPDATE_RATE, MAX_UPDATE_RATE) as u64,
+        )
+    }
+
+    /// Sets `update_interval_ms` from the `update_rate` in seconds of older
+    /// versions, once, when it was never written
+    pub fn migrate_update_rate(&mut self, config: &cosmic_config::Config) {
+        if config.get::<u32>("update_interval_ms").is_err()
+            && let Err(why) = self.set_update_interval_ms(config, self.update_rate as u32 * 1000)
+        {
+            eprintln!("error while migrating update rate: {why}");
         }
     }
+
+    /// Monthly data cap in bytes, `None` without a cap
+    pub fn get_data_cap(&self) -> Option<u64> {
+        (self.data_cap > 0).then(|| self.data_cap as u64 * self.unit_scale.base().pow(3))
+    }
+
+    pub fn get_color_for(&self, network_interface: Option<&str>) -> Option<&str> {
+        self.get_interface_settings(network_interface)?
+            .color
+            .as_deref()
+    }
 }
@@ -0,0 +1,349 @@
+use {
+    crate::{usage::UsageAccounting, vnstat},
+    chrono::{DateTime, Datelike, Local, NaiveDate, TimeDelta, Timelike},
+    rusqlite::{Connection, params},
+    std::{collections::BTreeMap, env, error::Error, fs, path::PathBuf},
+};
+
+/// Seconds covered by the detailed records
+const HOUR: i64 = 3600;
+/// Seconds covered by the records older ones are rolled up into
+const DAY: i64 = 24 * HOUR;
+
+/// Bytes received and sent by weekday, from Monday, and local hour.
+pub type Heatmap = [[u64; 24]; 7];
+
+/// `$XDG_DATA_HOME/<app_id>/usage.db`, defaulting to `~/.local/share`.
+fn database_path(app_id: &str) -> Option<PathBuf> {
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(data_home) if !data_home.is_empty() => PathBuf::from(data_home),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(data_home.join(app_id).join("usage.db"))
+}
+
+/// Interface name of the records of the monitored traffic
+const MONITORED: &str = "";
+
+/// Long-term usage of the monitored traffic and of every interface kept in
+/// SQLite as hourly records, rolled up into daily ones once they get old.
+pub struct UsageDatabase {
+    connection: Connection,
+    /// Traffic not written yet, by interface and Unix time of the start of
+    /// its hour
+    pending: BTreeMap<(String, i64), (u64, u64)>,
+}
+
+impl UsageDatabase {
+    pub fn open(app_id: &str) -> Result<Self, Box<dyn Error>> {
+        let path = database_path(app_id).ok_or("no data directory")?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS usage (
+                start INTEGER NOT NULL,
+                length INTEGER NOT NULL,
+                received_bytes INTEGER NOT NULL,
+                sent_bytes INTEGER NOT NULL,
+                PRIMARY KEY (start, length)
+            )",
+        )?;
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version < 1 {
+            // The first version only kept the monitored traffic, new
+            // databases are created with its table and migrated the same way
+            connection.execute_batch(
+                "BEGIN;
+                ALTER TABLE usage RENAME TO monitored_usage;
+                CREATE TABLE usage (
+                    interface TEXT NOT NULL,
+                    start INTEGER NOT NULL,
+                    length INTEGER NOT NULL,
+                    received_bytes INTEGER NOT NULL,
+                    sent_bytes INTEGER NOT NULL,
+                    PRIMARY KEY (interface, start, length)
+                );
+                INSERT INTO usage
+                    SELECT '', start, length, received_bytes, sent_bytes FROM monitored_usage;
+                DROP TABLE monitored_usage;
+                PRAGMA user_version = 1;
+                COMMIT;",
+            )?;
+        }
+        Ok(UsageDatabase {
+            connection,
+            pending: BTreeMap::new(),
+        })
+    }
+
+    /// Adds traffic of `network_interface`, or the monitored traffic if it
+    /// is [`MONITORED`], to the pending traffic of the current hour
+    fn add_pending(&mut self, network_interface: &str, received_bytes: u64, sent_bytes: u64) {
+        let hour = Local::now().timestamp().div_euclid(HOUR) * HOUR;
+        let pending = self
+            .pending
+            .entry((network_interface.to_string(), hour))
+            .or_default();
+        pending.0 += received_bytes;
+        pending.1 += sent_bytes;
+    }
+
+    /// Adds monitored traffic to the current hour, written on the next flush
+    pub fn record(&mut self, received_bytes: u64, sent_bytes: u64) {
+        self.add_pending(MONITORED, received_bytes, sent_bytes);
+    }
+
+    /// Adds traffic of `network_interface` to the current hour, written on
+    /// the next flush
+    pub fn record_interface(
+        &mut self,
+        network_interface: &str,
+        received_bytes: u64,
+        sent_bytes: u64,
+    ) {
+        self.add_pending(network_interface, received_bytes, sent_bytes);
+    }
+
+    /// Adds `records` spanning `length` seconds, keyed by their interface and
+    /// start, to the stored ones
+    fn add(
+        transaction: &rusqlite::Transaction,
+        records: impl IntoIterator<Item = ((String, i64), (u64, u64))>,
+        length: i64,
+    ) -> rusqlite::Result<()> {
+        let mut statement = transaction.prepare(
+            "INSERT INTO usage (interface, start, length, received_bytes, sent_bytes)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (interface, start, length) DO UPDATE SET
+                    received_bytes = received_bytes + excluded.received_bytes,
+                    sent_bytes = sent_bytes + excluded.sent_bytes",
+        )?;
+        for ((network_interface, start), (received_bytes, sent_bytes)) in records {
+            statement.execute(params![
+                network_interface,
+                start,
+                length,
+                received_bytes as i64,
+                sent_bytes as i64
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending traffic
+    pub fn flush(&mut self) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        Self::add(&transaction, std::mem::take(&mut self.pending), HOUR)?;
+        transaction.commit()
+    }
+
+    /// Rolls the hourly records older than `hourly_days` into records of
+    /// their local day, and deletes the records older than `retention_days`
+    pub fn compact(&mut self, hourly_days: u32, retention_days: u32) -> rusqlite::Result<()> {
+        let now = Local::now();
+        let hourly_oldest = (now - TimeDelta::days(hourly_days as i64)).timestamp();
+        let oldest = (now - TimeDelta::days(retention_days as i64)).timestamp();
+        let transaction = self.connection.transaction()?;
+
+        let mut days: BTreeMap<(String, i64), (u64, u64)> = BTreeMap::new();
+        {
+            let mut statement = transaction.prepare(
+                "SELECT interface, start, received_bytes, sent_bytes FROM usage
+                    WHERE length = ?1 AND start < ?2",
+            )?;
+            let rows = statement.query_map(params![HOUR, hourly_oldest], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?;
+            for row in rows {
+                let (network_interface, start, received_bytes, sent_bytes) = row?;
+                let Some(day) = local_midnight(start) else {
+                    continue;
+                };
+                let day = days.entry((network_interface, day)).or_default();
+                day.0 += received_bytes as u64;
+                day.1 += sent_bytes as u64;
+            }
+        }
+        Self::add(&transaction, days, DAY)?;
+        transaction.execute(
+            "DELETE FROM usage WHERE length = ?1 AND start < ?2",
+            params![HOUR, hourly_oldest],
+        )?;
+        transaction.execute("DELETE FROM usage WHERE start < ?1", params![oldest])?;
+        transaction.commit()
+    }
+
+    /// Adds the vnStat `records` of each interface that end before its
+    /// oldest record, and their sum as monitored traffic where it ends before
+    /// the oldest monitored record, so nothing is counted twice. Returns the
+    /// number of records added.
+    pub fn import(&mut self, records: &[vnstat::Record]) -> rusqlite::Result<usize> {
+        let transaction = self.connection.transaction()?;
+        let oldest = |network_interface: &str| {
+            transaction.query_row(
+                "SELECT MIN(start) FROM usage WHERE interface = ?1",
+                params![network_interface],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+        };
+
+        // Keyed by length, as records are added with a single one
+        let mut imported: BTreeMap<i64, BTreeMap<(String, i64), (u64, u64)>> = BTreeMap::new();
+        let mut monitored: BTreeMap<(i64, i64), (u64, u64)> = BTreeMap::new();
+        let mut interfaces_oldest = BTreeMap::new();
+        for record in records {
+            let (Some(start), Some(end)) = (midnight(record.start), midnight(record.end)) else {
+                continue;
+            };
+            let interface_oldest = match interfaces_oldest.get(&record.interface) {
+                Some(interface_oldest) => *interface_oldest,
+                None => {
+                    let interface_oldest = oldest(&record.interface)?;
+                    interfaces_oldest.insert(record.interface.clone(), interface_oldest);
+                    interface_oldest
+                }
+            };
+            if interface_oldest.is_some_and(|interface_oldest| end > interface_oldest) {
+                continue;
+            }
+            let traffic = imported
+                .entry(end - start)
+                .or_default()
+                .entry((record.interface.clone(), start))
+                .or_default();
+            traffic.0 += record.received_bytes;
+            traffic.1 += record.sent_bytes;
+            let traffic = monitored.entry((end - start, start)).or_default();
+            traffic.0 += record.received_bytes;
+            traffic.1 += record.sent_bytes;
+        }
+        let monitored_oldest = oldest(MONITORED)?;
+        for ((length, start), traffic) in monitored {
+            if monitored_oldest.is_none_or(|monitored_oldest| start + length <= monitored_oldest) {
+                imported
+                    .entry(length)
+                    .or_default()
+                    .insert((MONITORED.to_string(), start), traffic);
+            }
+        }
+
+        let mut count = 0;
+        for (length, records) in imported {
+            count += records.len();
+            Self::add(&transaction, records, length)?;
+        }
+        transaction.commit()?;
+        Ok(count)
+    }
+
+    /// Adds every record of `network_interface` to the local day it started
+    /// on
+    fn load_interface(
+        &self,
+        network_interface: &str,
+        usage: &mut UsageAccounting,
+    ) -> rusqlite::Result<()> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT start, received_bytes, sent_bytes FROM usage WHERE interface = ?1")?;
+        let rows = statement.query_map(params![network_interface], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (start, received_bytes, sent_bytes) = row?;
+            if let Some(time) = DateTime::from_timestamp(start, 0) {
+                usage.add(
+                    time.with_timezone(&Local).date_naive(),
+                    received_bytes as u64,
+                    sent_bytes as u64,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds every monitored record to the local day it started on
+    pub fn load(&self, usage: &mut UsageAccounting) -> rusqlite::Result<()> {
+        self.load_interface(MONITORED, usage)
+    }
+
+    /// Adds every record of each interface to the local day it started on
+    pub fn load_interfaces(
+        &self,
+        usage: &mut BTreeMap<String, UsageAccounting>,
+    ) -> rusqlite::Result<()> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT DISTINCT interface FROM usage WHERE interface != ?1")?;
+        let network_interfaces = statement
+            .query_map(params![MONITORED], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for network_interface in network_interfaces {
+            self.load_interface(
+                &network_interface,
+                usage.entry(network_interface.clone()).or_default(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Sums the monitored hourly records, pending ones included, by the
+    /// weekday and hour they started on
+    pub fn heatmap(&self) -> rusqlite::Result<Heatmap> {
+        let mut heatmap = [[0; 24]; 7];
+        let mut add = |start: i64, bytes: u64| {
+            if let Some(time) = DateTime::from_timestamp(start, 0) {
+                let time = time.with_timezone(&Local);
+                heatmap[time.weekday().num_days_from_monday() as usize][time.hour() as usize] +=
+                    bytes;
+            }
+        };
+        let mut statement = self.connection.prepare(
+            "SELECT start, received_bytes + sent_bytes FROM usage
+                WHERE interface = ?1 AND length = ?2",
+        )?;
+        let rows = statement.query_map(params![MONITORED, HOUR], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (start, bytes) = row?;
+            add(start, bytes as u64);
+        }
+        for ((network_interface, start), (received_bytes, sent_bytes)) in &self.pending {
+            if network_interface == MONITORED {
+                add(*start, received_bytes + sent_bytes);
+            }
+        }
+        Ok(heatmap)
+    }
+}
+
+/// Unix time of the local midnight starting `day`.
+fn midnight(day: NaiveDate) -> Option<i64> {
+    Some(
+        day.and_hms_opt(0, 0, 0)?
+            .and_local_timezone(Local)
+            .earliest()?
+            .timestamp(),
+    )
+}
+
+/// Unix time of the local midnight starting the day of `time`.
+fn local_midnight(time: i64) -> Option<i64> {
+    midnight(
+        DateTime::from_timestamp(time, 0)?
+            .with_timezone(&Local)
+            .date_naive(),
+    )
+}
@@ -0,0 +1,124 @@
+use {
+    crate::{config::EventKind, host},
+    serde_json::{Map, Value},
+};
+
+/// Something noteworthy that happened while monitoring.
+#[derive(Debug, Clone)]
+pub enum Event {
+    InterfaceChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    Roamed {
+        network_interface: String,
+        from: String,
+        to: String,
+    },
+    /// The usage of the billing cycle crossed `percent` of the data cap
+    DataCapCrossed {
+        percent: u8,
+        used: u64,
+        data_cap: u64,
+    },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::InterfaceChanged { .. } => EventKind::InterfaceChanged,
+            Event::Roamed { .. } => EventKind::Roamed,
+            Event::DataCapCrossed { .. } => EventKind::DataCapCrossed,
+        }
+    }
+
+    /// Event name and details, exported to hooks as environment variables and
+    /// available to webhook templates as `${NAME}` placeholders
+    pub fn variables(&self) -> Vec<(&'static str, String)> {
+        let mut variables = vec![("BITRATE_EVENT", self.kind().as_str().to_string())];
+        match self {
+            Event::InterfaceChanged { from, to } => {
+                variables.push(("BITRATE_INTERFACE_FROM", from.clone().unwrap_or_default()));
+                variables.push(("BITRATE_INTERFACE_TO", to.clone().unwrap_or_default()));
+            }
+            Event::Roamed {
+                network_interface,
+                from,
+                to,
+            } => {
+                variables.push(("BITRATE_INTERFACE", network_interface.clone()));
+                variables.push(("BITRATE_BSSID_FROM", from.clone()));
+                variables.push(("BITRATE_BSSID_TO", to.clone()));
+            }
+            Event::DataCapCrossed {
+                percent,
+                used,
+                data_cap,
+            } => {
+                variables.push(("BITRATE_DATA_CAP_PERCENT", percent.to_string()));
+                variables.push(("BITRATE_DATA_USED", used.to_string()));
+                variables.push(("BITRATE_DATA_CAP", data_cap.to_string()));
+            }
+        }
+        variables
+    }
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::InterfaceChanged => "interface-changed",
+            EventKind::Roamed => "roamed",
+            EventKind::DataCapCrossed => "data-cap-crossed",
+        }
+    }
+}
+
+/// Runs the user's hook command through `sh -c` without blocking the caller.
+pub fn run_hook(command: &str, event: &Event) {
+    let mut hook = host::command_with_env("sh", &event.variables());
+    hook.arg("-c").arg(command);
+
+    match hook.spawn() {
+        Ok(mut child) => {
+            // Reap the child so finished hooks don't linger as zombies
+            std::thread::spawn(move || child.wait());
+        }
+        Err(why) => eprintln!("error while running event hook: {why}"),
+    }
+}
+
+/// Fills the `${NAME}` placeholders of `template` with JSON-escaped event
+/// variables, or serializes all variables as one object if it is empty.
+pub fn get_webhook_body(template: &str, event: &Event) -> String {
+    if template.is_empty() {
+        let object: Map<String, Value> = event
+            .variables()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), Value::String(value)))
+            .collect();
+        return Value::Object(object).to_string();
+    }
+
+    let mut body = template.to_string();
+    for (key, value) in event.variables() {
+        let escaped = Value::String(value).to_string();
+        // Strip the quotes, the template decides where strings start and end
+        body = body.replace(&format!("${{{key}}}"), &escaped[1..escaped.len() - 1]);
+    }
+    body
+}
+
+/// POSTs the rendered template to `url` from a background thread.
+pub fn send_webhook(url: &str, template: &str, event: &Event) {
+    let url = url.to_string();
+    let body = get_webhook_body(template, event);
+    std::thread::spawn(move || {
+        if let Err(why) = ureq::post(&url)
+            .header("Content-Type", "application/json")
+            .send(&body)
+        {
+            eprintln!("error while sending webhook: {why}");
+        }
+    });
+}
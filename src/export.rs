@@ -0,0 +1,59 @@
+use {
+    crate::{history::History, usage::UsageAccounting},
+    resvg::usvg,
+    std::{
+        error::Error,
+        fs::{self, File},
+        io::{self, BufWriter, Write},
+        path::Path,
+    },
+};
+
+/// Pixels of the PNG images per pixel of the graph, for sharp lines
+const PNG_SCALE: f32 = 2.0;
+
+/// Writes the history and the daily usage to `path` as CSV, the samples
+/// first and the days after an empty line, each with a header row.
+pub fn write_csv(path: &Path, history: &History, usage: &UsageAccounting) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "time,received_bytes_per_second,sent_bytes_per_second")?;
+    for sample in history.samples() {
+        writeln!(
+            file,
+            "{},{},{}",
+            sample.time.to_rfc3339(),
+            sample.received,
+            sample.sent
+        )?;
+    }
+    writeln!(file)?;
+    writeln!(file, "date,received_bytes,sent_bytes")?;
+    for (day, usage) in usage.days() {
+        writeln!(file, "{day},{},{}", usage.received_bytes, usage.sent_bytes)?;
+    }
+    file.flush()
+}
+
+/// Writes the `svg` document to `path`, rendered as PNG if its extension is
+/// `png` and as is otherwise.
+pub fn write_image(path: &Path, svg: &str) -> Result<(), Box<dyn Error>> {
+    if !path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+    {
+        return Ok(fs::write(path, svg)?);
+    }
+    let tree = usvg::Tree::from_str(svg, &usvg::Options::default())?;
+    let size = tree.size();
+    let mut pixmap = tiny_skia::Pixmap::new(
+        (size.width() * PNG_SCALE).ceil() as u32,
+        (size.height() * PNG_SCALE).ceil() as u32,
+    )
+    .ok_or("empty graph")?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(PNG_SCALE, PNG_SCALE),
+        &mut pixmap.as_mut(),
+    );
+    Ok(pixmap.save_png(path)?)
+}
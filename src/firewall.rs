@@ -0,0 +1,60 @@
+use {crate::host, std::fs};
+
+/// An active firewall backend.
+#[derive(Debug, Clone)]
+pub struct FirewallStatus {
+    pub backend: &'static str,
+    /// Active zone or profile, where the backend has one
+    pub zone: Option<String>,
+}
+
+fn is_service_active(service: &str) -> bool {
+    host::command("systemctl")
+        .args(["is-active", "--quiet", service])
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn get_firewalld_status() -> Option<FirewallStatus> {
+    let output = host::command("firewall-cmd").arg("--state").output().ok()?;
+    if String::from_utf8_lossy(&output.stdout).trim() != "running" {
+        return None;
+    }
+    let zone = host::command("firewall-cmd")
+        .arg("--get-default-zone")
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|zone| !zone.is_empty());
+    Some(FirewallStatus {
+        backend: "firewalld",
+        zone,
+    })
+}
+
+fn get_ufw_status() -> Option<FirewallStatus> {
+    // `ufw status` requires root, but the configuration file is world readable
+    let ufw_config = fs::read_to_string("/etc/ufw/ufw.conf").ok()?;
+    let enabled = ufw_config
+        .lines()
+        .any(|line| line.trim().eq_ignore_ascii_case("ENABLED=yes"));
+    (enabled && is_service_active("ufw")).then_some(FirewallStatus {
+        backend: "ufw",
+        zone: None,
+    })
+}
+
+fn get_nftables_status() -> Option<FirewallStatus> {
+    is_service_active("nftables").then_some(FirewallStatus {
+        backend: "nftables",
+        zone: None,
+    })
+}
+
+/// Detects the first active firewall backend, checked from the most to the
+/// least specific since firewalld and ufw are themselves nftables frontends.
+pub fn get_firewall_status() -> Option<FirewallStatus> {
+    get_firewalld_status()
+        .or_else(get_ufw_status)
+        .or_else(get_nftables_status)
+}
@@ -0,0 +1,358 @@
+use {
+    crate::{config::GraphStyle, database::Heatmap, history::Sample, palette::Colors},
+    chrono::{DateTime, Local, TimeDelta},
+    cosmic::{
+        iced::{Color, Size},
+        widget::svg,
+    },
+    std::fmt::Write,
+};
+
+/// Width of the lines in pixels
+const STROKE_WIDTH: f32 = 1.5;
+
+/// `#rrggbb` notation of `color` for SVG attributes.
+fn hex(color: Color) -> String {
+    let [red, green, blue, _] = color.into_rgba8();
+    format!("#{red:02x}{green:02x}{blue:02x}")
+}
+
+/// Height of `speed` from the top of `height`, with `max` at the top, on a
+/// logarithmic scale if `log_scale`.
+fn y(speed: u64, height: f32, max: f32, log_scale: bool) -> f32 {
+    let fraction = if log_scale {
+        (speed as f32).ln_1p() / max.ln_1p()
+    } else {
+        speed as f32 / max
+    };
+    height - STROKE_WIDTH / 2.0 - fraction.min(1.0) * (height - STROKE_WIDTH)
+}
+
+/// `x,y` list of `points` for SVG attributes.
+fn coordinates<'a>(points: impl IntoIterator<Item = &'a (f32, f32)>) -> String {
+    let mut coordinates = String::new();
+    for (x, y) in points {
+        write!(coordinates, "{x:.1},{y:.1} ").unwrap();
+    }
+    coordinates
+}
+
+/// Line through `points`.
+fn polyline(points: &[(f32, f32)], color: Color) -> String {
+    format!(
+        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{STROKE_WIDTH}" stroke-linejoin="round"/>"#,
+        coordinates(points),
+        hex(color)
+    )
+}
+
+/// Translucent area between the `upper` and `lower` lines.
+fn area(upper: &[(f32, f32)], lower: &[(f32, f32)], color: Color) -> String {
+    format!(
+        r#"<polygon points="{}" fill="{}" fill-opacity="0.5"/>"#,
+        coordinates(upper.iter().chain(lower.iter().rev())),
+        hex(color)
+    )
+}
+
+/// Rounds `value` up to 1, 2 or 5 times a power of ten, for axes that are
+/// easy to read.
+pub fn nice_ceiling(value: u64) -> u64 {
+    let mut power = 1;
+    loop {
+        for factor in [1, 2, 5] {
+            if value <= factor * power {
+                return factor * power;
+            }
+        }
+        power *= 10;
+    }
+}
+
+/// Draws the download and upload speeds of `samples` as two lines on a
+/// shared scale, filling a `width` by `height` image.
+pub fn sparkline(samples: &[Sample], width: f32, height: f32, colors: &Colors) -> svg::Handle {
+    let max = samples
+        .iter()
+        .map(|sample| sample.received.max(sample.sent))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+    let step = width / (samples.len().max(2) - 1) as f32;
+    let points = |speed: fn(&Sample) -> u64| -> Vec<(f32, f32)> {
+        samples
+            .iter()
+            .enumerate()
+            .map(|(index, sample)| (index as f32 * step, y(speed(sample), height, max, false)))
+            .collect()
+    };
+    let image = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}">{}{}</svg>"#,
+        polyline(&points(|sample| sample.sent), colors.upload),
+        polyline(&points(|sample| sample.received), colors.download),
+    );
+    svg::Handle::from_memory(image.into_bytes())
+}
+
+/// Chart of the download and upload speeds over a time range, the newest at
+/// the right edge.
+pub struct Chart {
+    samples: Vec<Sample>,
+    /// Time of the newest sample, at the right edge
+    end: DateTime<Local>,
+    range: TimeDelta,
+    size: Size,
+    log_scale: bool,
+    style: GraphStyle,
+    /// Times of the events marked on the chart
+    events: Vec<DateTime<Local>>,
+    /// Times of the download and upload peaks
+    peaks: (Option<DateTime<Local>>, Option<DateTime<Local>>),
+    /// Color filling the chart, transparent if `None`
+    background: Option<Color>,
+}
+
+impl Chart {
+    /// Chart of the `samples` of the last `range`, ordered from the oldest
+    pub fn new(samples: &[Sample], range: TimeDelta, size: Size) -> Self {
+        let end = samples.last().map_or_else(Local::now, |sample| sample.time);
+        let start = samples.partition_point(|sample| sample.time < end - range);
+        Chart {
+            samples: samples[start..].to_vec(),
+            end,
+            range,
+            size,
+            log_scale: false,
+            style: GraphStyle::Lines,
+            events: Vec::new(),
+            peaks: (None, None),
+            background: None,
+        }
+    }
+
+    /// Scales the speeds logarithmically, so bursts do not flatten the rest
+    pub fn log_scale(mut self, log_scale: bool) -> Self {
+        self.log_scale = log_scale;
+        self
+    }
+
+    /// Whether the speeds are drawn as overlaid lines or stacked areas
+    pub fn style(mut self, style: GraphStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Marks the first update after each of the `events`
+    pub fn events(mut self, events: impl IntoIterator<Item = DateTime<Local>>) -> Self {
+        self.events = events.into_iter().collect();
+        self
+    }
+
+    /// Circles the updates of the `download` and `upload` peaks
+    pub fn peaks(
+        mut self,
+        download: Option<DateTime<Local>>,
+        upload: Option<DateTime<Local>>,
+    ) -> Self {
+        self.peaks = (download, upload);
+        self
+    }
+
+    /// Fills the chart with `background`, for images shown outside the popup
+    pub fn background(mut self, background: Color) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    /// Position of the sample at `index` in pixels
+    fn x(&self, index: usize) -> f32 {
+        let age = (self.end - self.samples[index].time).as_seconds_f32();
+        self.size.width * (1.0 - age / self.range.as_seconds_f32())
+    }
+
+    /// Index of the sample nearest to `x` pixels from the left edge
+    pub fn sample_at(&self, x: f32) -> Option<usize> {
+        (0..self.samples.len())
+            .min_by(|a, b| (self.x(*a) - x).abs().total_cmp(&(self.x(*b) - x).abs()))
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Index of the first update after `event`, `None` if it happened before
+    /// the chart or after the newest update
+    pub fn event_index(&self, event: DateTime<Local>) -> Option<usize> {
+        self.samples
+            .iter()
+            .position(|sample| sample.time >= event)
+            .filter(|index| *index > 0)
+    }
+
+    /// Index of the update measured at `time`, or of the average it was
+    /// merged into
+    pub fn sample_index(&self, time: DateTime<Local>) -> Option<usize> {
+        self.samples.iter().rposition(|sample| sample.time <= time)
+    }
+
+    /// Heights of the download and upload lines of `sample`, the upload on
+    /// top of the download when stacked
+    fn heights(&self, sample: &Sample, scale: f32) -> (f32, f32) {
+        let height = self.size.height;
+        let sent = match self.style {
+            GraphStyle::Lines => sample.sent,
+            GraphStyle::Stacked => sample.received + sample.sent,
+        };
+        (
+            y(sample.received, height, scale, self.log_scale),
+            y(sent, height, scale, self.log_scale),
+        )
+    }
+
+    /// Draws the chart as an image for the popup, see [`Chart::render`]
+    pub fn draw(
+        &self,
+        scale: f32,
+        hovered: Option<usize>,
+        colors: &Colors,
+        grid: Color,
+    ) -> svg::Handle {
+        svg::Handle::from_memory(self.render(scale, hovered, colors, grid).into_bytes())
+    }
+
+    /// SVG document of the chart with `scale` bytes per second at the top,
+    /// grid lines at every quarter, the events and peaks, and a crosshair on
+    /// the `hovered` sample.
+    pub fn render(
+        &self,
+        scale: f32,
+        hovered: Option<usize>,
+        colors: &Colors,
+        grid: Color,
+    ) -> String {
+        let Size { width, height } = self.size;
+        let (received, sent): (Vec<_>, Vec<_>) = self
+            .samples
+            .iter()
+            .enumerate()
+            .map(|(index, sample)| {
+                let (received, sent) = self.heights(sample, scale);
+                ((self.x(index), received), (self.x(index), sent))
+            })
+            .unzip();
+
+        let mut image = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}">"#
+        );
+        if let Some(background) = self.background {
+            write!(
+                image,
+                r#"<rect width="{width}" height="{height}" fill="{}"/>"#,
+                hex(background)
+            )
+            .unwrap();
+        }
+        for quarter in 0..=4 {
+            let y = (STROKE_WIDTH / 2.0 + quarter as f32 * (height - STROKE_WIDTH) / 4.0).round();
+            write!(
+                image,
+                r#"<line x1="0" y1="{y}" x2="{width}" y2="{y}" stroke="{}" stroke-opacity="0.25"/>"#,
+                hex(grid)
+            )
+            .unwrap();
+        }
+        if self.style == GraphStyle::Stacked
+            && let (Some((first, _)), Some((last, _))) = (received.first(), received.last())
+        {
+            image.push_str(&area(&sent, &received, colors.upload));
+            image.push_str(&area(
+                &received,
+                &[(*first, height), (*last, height)],
+                colors.download,
+            ));
+        }
+        for index in self
+            .events
+            .iter()
+            .filter_map(|event| self.event_index(*event))
+        {
+            let x = self.x(index);
+            write!(
+                image,
+                r#"<line x1="{x:.1}" y1="0" x2="{x:.1}" y2="{height}" stroke="{}" stroke-dasharray="3,3"/>"#,
+                hex(colors.warning)
+            )
+            .unwrap();
+        }
+        image.push_str(&polyline(&sent, colors.upload));
+        image.push_str(&polyline(&received, colors.download));
+        for (points, peak, color) in [
+            (&received, self.peaks.0, colors.download),
+            (&sent, self.peaks.1, colors.upload),
+        ] {
+            if let Some((x, y)) = peak
+                .and_then(|peak| self.sample_index(peak))
+                .map(|index| points[index])
+            {
+                write!(
+                    image,
+                    r#"<circle cx="{x:.1}" cy="{y:.1}" r="{}" fill="none" stroke="{}" stroke-width="{STROKE_WIDTH}"/>"#,
+                    STROKE_WIDTH * 3.0,
+                    hex(color)
+                )
+                .unwrap();
+            }
+        }
+        if let Some(index) = hovered.filter(|index| *index < self.samples.len()) {
+            let x = self.x(index);
+            write!(
+                image,
+                r#"<line x1="{x:.1}" y1="0" x2="{x:.1}" y2="{height}" stroke="{}" stroke-opacity="0.5"/>"#,
+                hex(grid)
+            )
+            .unwrap();
+            for ((_, y), color) in [
+                (sent[index], colors.upload),
+                (received[index], colors.download),
+            ] {
+                write!(
+                    image,
+                    r#"<circle cx="{x:.1}" cy="{y:.1}" r="{}" fill="{}"/>"#,
+                    STROKE_WIDTH * 2.0,
+                    hex(color)
+                )
+                .unwrap();
+            }
+        }
+        image.push_str("</svg>");
+        image
+    }
+}
+
+/// Draws `heatmap` as a grid of `cell` pixel squares, one row per weekday,
+/// more opaque the more traffic they had.
+pub fn heatmap(heatmap: &Heatmap, cell: f32, color: Color) -> svg::Handle {
+    let max = heatmap.iter().flatten().copied().max().unwrap_or(0).max(1) as f32;
+    let mut image = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
+        cell * 24.0,
+        cell * 7.0
+    );
+    for (weekday, hours) in heatmap.iter().enumerate() {
+        for (hour, bytes) in hours.iter().enumerate() {
+            write!(
+                image,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="2" fill="{}" fill-opacity="{:.2}"/>"#,
+                hour as f32 * cell + 1.0,
+                weekday as f32 * cell + 1.0,
+                cell - 2.0,
+                cell - 2.0,
+                hex(color),
+                0.1 + 0.9 * *bytes as f32 / max
+            )
+            .unwrap();
+        }
+    }
+    image.push_str("</svg>");
+    svg::Handle::from_memory(image.into_bytes())
+}
@@ -0,0 +1,341 @@
+use {
+    chrono::{DateTime, Local, TimeDelta},
+    std::collections::VecDeque,
+};
+
+/// Seconds the samples are kept as measured for, older ones are averaged
+pub const FULL_RESOLUTION_AGE: u32 = 10 * 60;
+
+/// Seconds spanned by the averages of the samples up to a day old
+const MINUTE: i64 = 60;
+
+/// Seconds spanned by the averages of older samples
+const HOUR: i64 = 3600;
+
+/// Seconds after which the minute averages are merged into hourly ones
+const MINUTE_TIER_AGE: i64 = 24 * HOUR;
+
+/// Download and upload speeds measured at one update, or averaged over
+/// several of them.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub time: DateTime<Local>,
+    /// Speeds in bytes per second
+    pub received: u64,
+    pub sent: u64,
+}
+
+/// Lowest, highest and mean speed of one direction, in bytes per second.
+#[derive(Debug, Clone, Copy)]
+pub struct Statistics {
+    pub min: u64,
+    pub max: u64,
+    pub mean: u64,
+}
+
+/// Averaged samples, each with the number of samples averaged into it.
+type Tier = VecDeque<(Sample, u64)>;
+
+/// Averages `sample`, standing for `weight` samples, into the newest one of
+/// `tier` while it is within `span` seconds of it.
+fn merge(tier: &mut Tier, sample: Sample, weight: u64, span: i64) {
+    match tier.back_mut() {
+        Some((newest, merged)) if (sample.time - newest.time).num_seconds() < span => {
+            let average =
+                |total: u64, value: u64| (total * *merged + value * weight) / (*merged + weight);
+            newest.received = average(newest.received, sample.received);
+            newest.sent = average(newest.sent, sample.sent);
+            *merged += weight;
+        }
+        _ => tier.push_back((sample, weight)),
+    }
+}
+
+/// Drops the samples of `tier` older than `oldest`.
+fn drop_older(tier: &mut Tier, oldest: DateTime<Local>) {
+    while tier.front().is_some_and(|(sample, _)| sample.time < oldest) {
+        tier.pop_front();
+    }
+}
+
+/// Samples of the retention period, kept as measured for ten minutes, then
+/// averaged per minute for a day and per hour beyond, so its size stays
+/// bounded whatever the update rate and retention.
+#[derive(Debug)]
+pub struct History {
+    /// Hourly averages of the samples older than a day
+    hours: Tier,
+    /// Minute averages of the samples older than ten minutes
+    minutes: Tier,
+    samples: VecDeque<Sample>,
+    retention: TimeDelta,
+}
+
+impl History {
+    pub fn new(retention_seconds: u32) -> Self {
+        History {
+            hours: VecDeque::new(),
+            minutes: VecDeque::new(),
+            samples: VecDeque::new(),
+            retention: TimeDelta::seconds(retention_seconds as i64),
+        }
+    }
+
+    pub fn set_retention(&mut self, retention_seconds: u32) {
+        self.retention = TimeDelta::seconds(retention_seconds as i64);
+        self.expire(Local::now());
+    }
+
+    /// Adds the speeds measured now
+    pub fn push(&mut self, received: u64, sent: u64) {
+        let time = Local::now();
+        self.samples.push_back(Sample {
+            time,
+            received,
+            sent,
+        });
+        self.expire(time);
+    }
+
+    /// Replaces the samples with saved ones, each tier from its oldest sample
+    /// to its newest and the averages with the number of samples averaged
+    /// into them
+    pub fn restore(
+        &mut self,
+        hours: impl IntoIterator<Item = (Sample, u64)>,
+        minutes: impl IntoIterator<Item = (Sample, u64)>,
+        samples: impl IntoIterator<Item = Sample>,
+    ) {
+        self.hours = hours.into_iter().collect();
+        self.minutes = minutes.into_iter().collect();
+        self.samples = samples.into_iter().collect();
+        self.expire(Local::now());
+    }
+
+    /// Averages the samples into the tier of their age, and drops the
+    /// samples older than the retention
+    fn expire(&mut self, now: DateTime<Local>) {
+        let full_resolution_oldest = now - TimeDelta::seconds(FULL_RESOLUTION_AGE as i64);
+        while let Some(sample) = self
+            .samples
+            .front()
+            .filter(|sample| sample.time < full_resolution_oldest)
+            .copied()
+        {
+            self.samples.pop_front();
+            merge(&mut self.minutes, sample, 1, MINUTE);
+        }
+        let minute_tier_oldest = now - TimeDelta::seconds(MINUTE_TIER_AGE);
+        while let Some((sample, weight)) = self
+            .minutes
+            .front()
+            .filter(|(sample, _)| sample.time < minute_tier_oldest)
+            .copied()
+        {
+            self.minutes.pop_front();
+            merge(&mut self.hours, sample, weight, HOUR);
+        }
+
+        let oldest = now - self.retention;
+        drop_older(&mut self.hours, oldest);
+        drop_older(&mut self.minutes, oldest);
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| sample.time < oldest)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Samples from the oldest to the newest
+    pub fn samples(&self) -> impl DoubleEndedIterator<Item = &Sample> {
+        self.hours
+            .iter()
+            .chain(&self.minutes)
+            .map(|(sample, _)| sample)
+            .chain(&self.samples)
+    }
+
+    /// Hourly averages with the number of samples averaged into each, from
+    /// the oldest to the newest
+    pub fn hour_averages(&self) -> impl Iterator<Item = (Sample, u64)> {
+        self.hours.iter().copied()
+    }
+
+    /// Minute averages with the number of samples averaged into each, from
+    /// the oldest to the newest
+    pub fn minute_averages(&self) -> impl Iterator<Item = (Sample, u64)> {
+        self.minutes.iter().copied()
+    }
+
+    /// Samples kept as measured, from the oldest to the newest
+    pub fn measured(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    /// The newest `count` samples, from the oldest of them to the newest
+    pub fn latest(&self, count: usize) -> impl Iterator<Item = &Sample> {
+        let skipped =
+            (self.hours.len() + self.minutes.len() + self.samples.len()).saturating_sub(count);
+        self.samples().skip(skipped)
+    }
+
+    /// Download and upload statistics over the retention, `None` while
+    /// empty. Averaged samples weigh as much as the samples they replace.
+    pub fn statistics(&self) -> Option<(Statistics, Statistics)> {
+        let weighted = self
+            .hours
+            .iter()
+            .chain(&self.minutes)
+            .copied()
+            .chain(self.samples.iter().map(|sample| (*sample, 1)));
+        let empty = Statistics {
+            min: u64::MAX,
+            max: 0,
+            mean: 0,
+        };
+        let (mut received, mut sent) = (empty, empty);
+        let mut count = 0;
+        // Sums are kept in the means until the division at the end
+        for (sample, weight) in weighted {
+            for (statistics, speed) in [(&mut received, sample.received), (&mut sent, sample.sent)]
+            {
+                statistics.min = statistics.min.min(speed);
+                statistics.max = statistics.max.max(speed);
+                statistics.mean += speed * weight;
+            }
+            count += weight;
+        }
+        if count == 0 {
+            return None;
+        }
+        received.mean /= count;
+        sent.mean /= count;
+        Some((received, sent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Midnight UTC on 2026-01-01, whatever the local time zone
+    fn start() -> DateTime<Local> {
+        DateTime::from_timestamp(1_767_225_600, 0)
+            .unwrap()
+            .with_timezone(&Local)
+    }
+
+    fn sample(time: DateTime<Local>, received: u64) -> Sample {
+        Sample {
+            time,
+            received,
+            sent: received / 2,
+        }
+    }
+
+    /// Adds a sample measured at `seconds` after the start, as `push` does
+    fn record(history: &mut History, seconds: i64, received: u64) {
+        let time = start() + TimeDelta::seconds(seconds);
+        history.samples.push_back(sample(time, received));
+        history.expire(time);
+    }
+
+    fn speeds(tier: impl Iterator<Item = (Sample, u64)>) -> Vec<(u64, u64)> {
+        tier.map(|(sample, weight)| (sample.received, weight))
+            .collect()
+    }
+
+    #[test]
+    fn averages_samples_per_minute_after_ten_minutes() {
+        let mut history = History::new(2 * 24 * 3600);
+        record(&mut history, 0, 100);
+        record(&mut history, 30, 300);
+        record(&mut history, 60, 500);
+        assert_eq!(history.minute_averages().count(), 0);
+        record(&mut history, FULL_RESOLUTION_AGE as i64 + 61, 0);
+        assert_eq!(speeds(history.minute_averages()), [(200, 2), (500, 1)]);
+        assert_eq!(history.measured().count(), 1);
+    }
+
+    #[test]
+    fn averages_minutes_per_hour_after_a_day() {
+        let mut history = History::new(3 * 24 * 3600);
+        record(&mut history, 0, 100);
+        record(&mut history, 60, 300);
+        record(&mut history, HOUR, 700);
+        record(&mut history, MINUTE_TIER_AGE + HOUR + 1, 0);
+        assert_eq!(speeds(history.hour_averages()), [(200, 2), (700, 1)]);
+        assert_eq!(history.minute_averages().count(), 0);
+    }
+
+    #[test]
+    fn merges_averages_by_their_weights() {
+        let mut tier = Tier::new();
+        tier.push_back((sample(start(), 100), 3));
+        merge(
+            &mut tier,
+            sample(start() + TimeDelta::seconds(30), 500),
+            1,
+            MINUTE,
+        );
+        assert_eq!(speeds(tier.iter().copied()), [(200, 4)]);
+        merge(
+            &mut tier,
+            sample(start() + TimeDelta::seconds(60), 500),
+            2,
+            MINUTE,
+        );
+        assert_eq!(speeds(tier.iter().copied()), [(200, 4), (500, 2)]);
+    }
+
+    #[test]
+    fn drops_samples_older_than_the_retention() {
+        let mut history = History::new(2 * 24 * 3600);
+        record(&mut history, 0, 100);
+        record(&mut history, 25 * HOUR, 200);
+        assert_eq!(history.hour_averages().count(), 1);
+        record(&mut history, 49 * HOUR, 300);
+        assert_eq!(history.hour_averages().count(), 0);
+        let times: Vec<_> = history.samples().map(|sample| sample.time).collect();
+        assert_eq!(
+            times,
+            [
+                start() + TimeDelta::seconds(25 * HOUR),
+                start() + TimeDelta::seconds(49 * HOUR)
+            ]
+        );
+    }
+
+    #[test]
+    fn statistics_weigh_the_averages() {
+        let mut history = History::new(2 * 24 * 3600);
+        assert!(history.statistics().is_none());
+        history.hours.push_back((sample(start(), 100), 3));
+        history
+            .minutes
+            .push_back((sample(start() + TimeDelta::seconds(HOUR), 400), 1));
+        history
+            .samples
+            .push_back(sample(start() + TimeDelta::seconds(2 * HOUR), 200));
+        let (received, sent) = history.statistics().unwrap();
+        assert_eq!((received.min, received.max, received.mean), (100, 400, 180));
+        assert_eq!((sent.min, sent.max, sent.mean), (50, 200, 90));
+    }
+
+    #[test]
+    fn latest_spans_the_tiers() {
+        let mut history = History::new(2 * 24 * 3600);
+        history.hours.push_back((sample(start(), 100), 3));
+        history
+            .minutes
+            .push_back((sample(start() + TimeDelta::seconds(HOUR), 400), 1));
+        history
+            .samples
+            .push_back(sample(start() + TimeDelta::seconds(2 * HOUR), 200));
+        let latest: Vec<_> = history.latest(2).map(|sample| sample.received).collect();
+        assert_eq!(latest, [400, 200]);
+        assert_eq!(history.latest(5).count(), 3);
+    }
+}
@@ -0,0 +1,24 @@
+use std::{path::Path, process::Command};
+
+/// Builds a command that runs `program` on the host, escaping the Flatpak
+/// sandbox through `flatpak-spawn` when needed.
+pub fn command(program: &str) -> Command {
+    command_with_env(program, &[])
+}
+
+/// Like [`command`], with extra environment variables for the host process.
+pub fn command_with_env(program: &str, vars: &[(&str, String)]) -> Command {
+    if Path::new("/.flatpak-info").exists() {
+        let mut command = Command::new("flatpak-spawn");
+        command.arg("--host");
+        for (key, value) in vars {
+            command.arg(format!("--env={key}={value}"));
+        }
+        command.arg(program);
+        command
+    } else {
+        let mut command = Command::new(program);
+        command.envs(vars.iter().map(|(key, value)| (key, value)));
+        command
+    }
+}
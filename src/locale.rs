@@ -0,0 +1,86 @@
+use std::{ffi::CStr, ptr, sync::LazyLock};
+
+/// Separators of numbers in the user's `LC_NUMERIC` locale.
+struct NumberFormat {
+    decimal_separator: String,
+    group_separator: String,
+}
+
+static NUMBER_FORMAT: LazyLock<NumberFormat> = LazyLock::new(|| {
+    let mut number_format = NumberFormat {
+        decimal_separator: ".".to_string(),
+        group_separator: String::new(),
+    };
+    // A locale object of its own leaves the process locale untouched for
+    // the C libraries parsing numbers
+    // SAFETY: the locale is valid until it is freed and the strings
+    // returned for it are copied before that
+    unsafe {
+        let locale = libc::newlocale(libc::LC_NUMERIC_MASK, c"".as_ptr(), ptr::null_mut());
+        if locale.is_null() {
+            return number_format;
+        }
+        let decimal_separator = CStr::from_ptr(libc::nl_langinfo_l(libc::RADIXCHAR, locale));
+        if !decimal_separator.is_empty() {
+            number_format.decimal_separator = decimal_separator.to_string_lossy().into_owned();
+        }
+        number_format.group_separator = CStr::from_ptr(libc::nl_langinfo_l(libc::THOUSEP, locale))
+            .to_string_lossy()
+            .into_owned();
+        libc::freelocale(locale);
+    }
+    number_format
+});
+
+/// Rewrites a number formatted by Rust, e.g. `12345.6`, with the decimal
+/// separator and digit grouping of the user's locale.
+pub fn localize_number(number: &str) -> String {
+    let NumberFormat {
+        decimal_separator,
+        group_separator,
+    } = &*NUMBER_FORMAT;
+    let (integer, fraction) = match number.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number, None),
+    };
+
+    let mut localized = String::new();
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            localized.push_str(group_separator);
+        }
+        localized.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        localized.push_str(decimal_separator);
+        localized.push_str(fraction);
+    }
+    localized
+}
+
+/// The widest localized number of `digits` digits, with a group or decimal
+/// separator, to measure the room numbers take.
+pub fn widest_number(digits: usize) -> String {
+    let grouped = localize_number(&"0".repeat(digits));
+    if grouped.chars().all(|c| c.is_ascii_digit()) {
+        let integer = "0".repeat(digits.saturating_sub(2));
+        localize_number(&format!("{integer}.00"))
+    } else {
+        grouped
+    }
+}
+
+/// Pads a localized number with leading figure spaces to the width of
+/// `width` digits and a separator, so it keeps its width between updates.
+pub fn pad_number(number: &str, width: usize) -> String {
+    const FIGURE_SPACE: char = '\u{2007}';
+    const PUNCTUATION_SPACE: char = '\u{2008}';
+    let digits = number.chars().filter(char::is_ascii_digit).count();
+    let mut padded: String =
+        std::iter::repeat_n(FIGURE_SPACE, width.saturating_sub(digits)).collect();
+    if number.chars().all(|c| c.is_ascii_digit()) {
+        padded.push(PUNCTUATION_SPACE);
+    }
+    padded.push_str(number);
+    padded
+}
@@ -1,7 +1,30 @@
 mod app;
 mod config;
+mod database;
+mod events;
+mod export;
+mod firewall;
+mod graph;
+mod history;
+mod host;
 mod i18n;
+mod locale;
+mod netlink;
 mod network;
+mod nm;
+mod notification;
+mod palette;
+mod persistence;
+mod plugin;
+mod ports;
+mod reachability;
+mod shaping;
+mod speech;
+mod stats;
+#[cfg(feature = "otlp")]
+mod telemetry;
+mod usage;
+mod vnstat;
 
 fn main() -> cosmic::iced::Result {
     let requested_languages = i18n_embed::DesktopLanguageRequester::requested_languages();
@@ -0,0 +1,243 @@
+use {
+    crate::stats::Counters,
+    std::{
+        ffi::CStr,
+        io, mem,
+        os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    },
+};
+
+/// Size of `struct nlmsghdr`
+const HEADER_LENGTH: usize = mem::size_of::<libc::nlmsghdr>();
+
+/// Size of `struct ifinfomsg`, following the header of link messages
+const IFINFO_LENGTH: usize = 16;
+
+/// `IF_OPER_UP`, the operational state of interfaces passing packets
+const IF_OPER_UP: u8 = 6;
+
+/// Opens a `NETLINK_ROUTE` socket.
+fn open_socket() -> io::Result<OwnedFd> {
+    // SAFETY: plain socket(2) call, the descriptor is owned right away
+    unsafe {
+        let fd = libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        );
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+/// Rounds `length` up to the 4 bytes messages and attributes are padded to.
+fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+/// Reads the `N` bytes at `offset` of `buffer`, `None` past its end.
+fn bytes<const N: usize>(buffer: &[u8], offset: usize) -> Option<[u8; N]> {
+    buffer.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
+/// A `NETLINK_ROUTE` socket subscribed to link and address changes.
+pub struct LinkMonitor {
+    socket: OwnedFd,
+}
+
+impl LinkMonitor {
+    pub fn new() -> io::Result<Self> {
+        let socket = open_socket()?;
+
+        // SAFETY: sockaddr_nl is plain data for which zero is a valid value
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups =
+            (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+        // SAFETY: address outlives the call and its size is passed along
+        let result = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(LinkMonitor { socket })
+    }
+
+    /// Blocks until the kernel reports a change, then drains the messages
+    /// queued by the same change so a burst wakes the caller only once.
+    pub fn wait(&self) -> io::Result<()> {
+        let mut buffer = [0u8; 8192];
+        let mut flags = 0;
+        loop {
+            // SAFETY: the buffer is valid for writes of its length
+            let received = unsafe {
+                libc::recv(
+                    self.socket.as_raw_fd(),
+                    buffer.as_mut_ptr().cast(),
+                    buffer.len(),
+                    flags,
+                )
+            };
+            if received < 0 {
+                let error = io::Error::last_os_error();
+                return match error.kind() {
+                    io::ErrorKind::WouldBlock if flags != 0 => Ok(()),
+                    io::ErrorKind::Interrupted => continue,
+                    // The kernel dropped messages, which is still a change
+                    _ if error.raw_os_error() == Some(libc::ENOBUFS) => Ok(()),
+                    _ => Err(error),
+                };
+            }
+            flags = libc::MSG_DONTWAIT;
+        }
+    }
+}
+
+/// Name, state and counters of an interface, as reported by the kernel.
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub name: String,
+    /// Whether the interface is up with a carrier
+    pub active: bool,
+    pub counters: Counters,
+}
+
+/// Reads the link of an `RTM_NEWLINK` message, `None` if it lacks its name
+/// or 64-bit statistics.
+fn parse_link(message: &[u8]) -> Option<Link> {
+    let (mut name, mut operstate, mut carrier, mut counters) = (None, None, None, None);
+    let mut offset = HEADER_LENGTH + IFINFO_LENGTH;
+    while let Some(header) = bytes::<4>(message, offset) {
+        let length = u16::from_ne_bytes([header[0], header[1]]) as usize;
+        let kind = u16::from_ne_bytes([header[2], header[3]]);
+        let Some(data) = message
+            .get(offset + 4..offset + length)
+            .filter(|_| length >= 4)
+        else {
+            break;
+        };
+        match kind {
+            libc::IFLA_IFNAME => {
+                name = CStr::from_bytes_until_nul(data)
+                    .ok()
+                    .and_then(|name| name.to_str().ok())
+                    .map(str::to_string);
+            }
+            libc::IFLA_OPERSTATE => operstate = data.first().copied(),
+            libc::IFLA_CARRIER => carrier = data.first().copied(),
+            libc::IFLA_STATS64 => {
+                // struct rtnl_link_stats64 starts with the packet then the
+                // byte counters, received first
+                let field = |index: usize| bytes::<8>(data, index * 8).map(u64::from_ne_bytes);
+                counters = Some(Counters {
+                    received_packets: field(0)?,
+                    sent_packets: field(1)?,
+                    received_bytes: field(2)?,
+                    sent_bytes: field(3)?,
+                });
+            }
+            _ => {}
+        }
+        offset += align(length);
+    }
+    Some(Link {
+        name: name?,
+        active: operstate == Some(IF_OPER_UP) && carrier == Some(1),
+        counters: counters?,
+    })
+}
+
+/// A `NETLINK_ROUTE` socket reading the statistics of every interface in one
+/// round-trip.
+pub struct LinkStatistics {
+    socket: OwnedFd,
+}
+
+impl LinkStatistics {
+    pub fn new() -> io::Result<Self> {
+        Ok(LinkStatistics {
+            socket: open_socket()?,
+        })
+    }
+
+    /// Dumps every interface with `RTM_GETLINK`
+    pub fn dump(&self) -> io::Result<Vec<Link>> {
+        let mut request = Vec::with_capacity(HEADER_LENGTH + IFINFO_LENGTH);
+        request.extend_from_slice(&((HEADER_LENGTH + IFINFO_LENGTH) as u32).to_ne_bytes());
+        request.extend_from_slice(&libc::RTM_GETLINK.to_ne_bytes());
+        request.extend_from_slice(&((libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16).to_ne_bytes());
+        // Sequence number and port ID, replies come back on this socket only
+        request.extend_from_slice(&[0; 8]);
+        // struct ifinfomsg, left empty to match every interface
+        request.push(libc::AF_UNSPEC as u8);
+        request.resize(HEADER_LENGTH + IFINFO_LENGTH, 0);
+        // SAFETY: the request is valid for reads of its length, and an
+        // unbound netlink socket sends to the kernel
+        let sent = unsafe {
+            libc::send(
+                self.socket.as_raw_fd(),
+                request.as_ptr().cast(),
+                request.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut links = Vec::new();
+        let mut buffer = vec![0u8; 32 * 1024];
+        loop {
+            // SAFETY: the buffer is valid for writes of its length
+            let received = unsafe {
+                libc::recv(
+                    self.socket.as_raw_fd(),
+                    buffer.as_mut_ptr().cast(),
+                    buffer.len(),
+                    0,
+                )
+            };
+            if received < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            let messages = &buffer[..received as usize];
+            let mut offset = 0;
+            while let Some(header) = bytes::<HEADER_LENGTH>(messages, offset) {
+                let length =
+                    u32::from_ne_bytes([header[0], header[1], header[2], header[3]]) as usize;
+                let kind = u16::from_ne_bytes([header[4], header[5]]);
+                let Some(message) = messages
+                    .get(offset..offset + length)
+                    .filter(|_| length >= HEADER_LENGTH)
+                else {
+                    break;
+                };
+                match kind as libc::c_int {
+                    libc::NLMSG_DONE => return Ok(links),
+                    libc::NLMSG_ERROR => {
+                        let error = bytes::<4>(message, HEADER_LENGTH).map(i32::from_ne_bytes);
+                        return Err(match error {
+                            Some(error) if error < 0 => io::Error::from_raw_os_error(-error),
+                            _ => io::Error::other("unexpected netlink acknowledgement"),
+                        });
+                    }
+                    _ if kind == libc::RTM_NEWLINK => links.extend(parse_link(message)),
+                    _ => {}
+                }
+                offset += align(length);
+            }
+        }
+    }
+}
@@ -1,47 +1,368 @@
-use std::fs;
+use {
+    crate::host,
+    std::{
+        ffi::CStr,
+        fs, io,
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket},
+        path::Path,
+        process::Stdio,
+    },
+};
 
-pub fn get_network_interfaces() -> Vec<String> {
-    let mut interfaces: Vec<String> = Vec::new();
+/// An address assigned to a network interface.
+pub struct InterfaceAddress {
+    pub interface: String,
+    pub address: IpAddr,
+    /// IPv4 broadcast address, if the interface supports broadcasting
+    pub broadcast: Option<Ipv4Addr>,
+}
 
-    let paths = fs::read_dir("/sys/class/net").ok().unwrap();
-    for entry in paths.flatten() {
-        let iface = entry.file_name().into_string().ok().unwrap();
+/// Matches `name` against a glob `pattern` supporting `*` and `?`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Pattern position after the last `*` and the name position it resumes at
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
 
-        // 1. Skip loopback
-        if iface == "lo" {
-            continue;
+/// Whether `network_interface` matches one of the glob `patterns`.
+pub fn is_excluded(network_interface: &str, patterns: &[String]) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| glob_match(pattern.trim(), network_interface))
+}
+
+/// Lists the interfaces owning a default route, IPv4 routes first and each
+/// family ordered by metric, read from `/proc/net/route` and
+/// `/proc/net/ipv6_route`.
+pub fn get_default_route_interfaces() -> Vec<String> {
+    const RTF_UP: u32 = 0x0001;
+    let parse_hex = |field: &str| u32::from_str_radix(field, 16).ok();
+
+    // Iface Destination Gateway Flags RefCnt Use Metric Mask ...
+    let mut ipv4_routes: Vec<(u32, String)> = fs::read_to_string("/proc/net/route")
+        .unwrap_or_default()
+        .lines()
+        .skip(1)
+        .filter_map(|route| {
+            let fields: Vec<&str> = route.split_whitespace().collect();
+            if fields.len() < 8 || fields[1] != "00000000" || fields[7] != "00000000" {
+                return None;
+            }
+            if parse_hex(fields[3])? & RTF_UP == 0 {
+                return None;
+            }
+            Some((parse_hex(fields[6])?, fields[0].to_string()))
+        })
+        .collect();
+    ipv4_routes.sort();
+
+    // Destination PrefixLength Source PrefixLength NextHop Metric RefCnt Use Flags Iface
+    let mut ipv6_routes: Vec<(u32, String)> = fs::read_to_string("/proc/net/ipv6_route")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|route| {
+            let fields: Vec<&str> = route.split_whitespace().collect();
+            // Unreachable default routes are attached to the loopback interface
+            if fields.len() < 10 || fields[1] != "00" || fields[9] == "lo" {
+                return None;
+            }
+            if parse_hex(fields[8])? & RTF_UP == 0 {
+                return None;
+            }
+            Some((parse_hex(fields[5])?, fields[9].to_string()))
+        })
+        .collect();
+    ipv6_routes.sort();
+
+    ipv4_routes
+        .into_iter()
+        .chain(ipv6_routes)
+        .map(|(_, network_interface)| network_interface)
+        .collect()
+}
+
+/// Reads the IPv4 default gateway routed through `network_interface` from
+/// `/proc/net/route`.
+pub fn get_default_gateway(network_interface: &str) -> Option<Ipv4Addr> {
+    let routes = fs::read_to_string("/proc/net/route").ok()?;
+    // Skip the header line
+    routes.lines().skip(1).find_map(|route| {
+        let fields: Vec<&str> = route.split_whitespace().collect();
+        if fields.len() < 3 || fields[0] != network_interface || fields[1] != "00000000" {
+            return None;
         }
+        // Addresses are printed as native endian integers of network order bytes
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        // Point-to-point links have default routes without a gateway
+        Some(Ipv4Addr::from(gateway.to_ne_bytes())).filter(|gateway| !gateway.is_unspecified())
+    })
+}
+
+/// Sends a single ping to `address` and waits up to a second for the reply.
+pub fn ping(address: IpAddr) -> Option<bool> {
+    host::command("ping")
+        .args(["-c", "1", "-W", "1", &address.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()
+        .map(|status| status.success())
+}
+
+pub fn is_wireless(network_interface: &str) -> bool {
+    Path::new("/sys/class/net")
+        .join(network_interface)
+        .join("wireless")
+        .exists()
+}
+
+/// Whether `network_interface` is a software device such as a bridge,
+/// tun/tap, veth or container interface.
+pub fn is_virtual(network_interface: &str) -> bool {
+    let path = Path::new("/sys/class/net").join(network_interface);
+    // Interfaces backed by hardware link to their bus device
+    if !path.join("device").exists() {
+        return true;
+    }
+    fs::read_to_string(path.join("uevent"))
+        .unwrap_or_default()
+        .lines()
+        .any(|line| matches!(line, "DEVTYPE=bridge" | "DEVTYPE=vlan" | "DEVTYPE=macvlan"))
+}
+
+/// Whether `network_interface` is a VPN tunnel, i.e. a tun/tap or WireGuard
+/// interface.
+pub fn is_vpn(network_interface: &str) -> bool {
+    const ARPHRD_NONE: &str = "65534";
+    let path = Path::new("/sys/class/net").join(network_interface);
+    path.join("tun_flags").exists()
+        || fs::read_to_string(path.join("type")).is_ok_and(|kind| kind.trim() == ARPHRD_NONE)
+        || fs::read_to_string(path.join("uevent"))
+            .is_ok_and(|uevent| uevent.lines().any(|line| line == "DEVTYPE=wireguard"))
+}
+
+/// Shortens an interface name to its first two letters, e.g. `wlan0` to `WL`.
+pub fn abbreviate_interface_name(network_interface: &str) -> String {
+    network_interface
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .take(2)
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Reads the kernel index of `network_interface`, which stays the same when
+/// the interface is renamed.
+pub fn get_interface_index(network_interface: &str) -> Option<u32> {
+    fs::read_to_string(
+        Path::new("/sys/class/net")
+            .join(network_interface)
+            .join("ifindex"),
+    )
+    .ok()?
+    .trim()
+    .parse()
+    .ok()
+}
+
+/// Reads the bond or bridge `network_interface` is enslaved to.
+pub fn get_master(network_interface: &str) -> Option<String> {
+    fs::read_link(
+        Path::new("/sys/class/net")
+            .join(network_interface)
+            .join("master"),
+    )
+    .ok()?
+    .file_name()?
+    .to_str()
+    .map(str::to_string)
+}
 
-        let path = entry.path();
+/// Leaves out interfaces whose bond or bridge is listed as well, as the
+/// master already carries their traffic.
+pub fn without_enslaved(network_interfaces: &[String]) -> impl Iterator<Item = &String> {
+    network_interfaces.iter().filter(|network_interface| {
+        get_master(network_interface).is_none_or(|master| !network_interfaces.contains(&master))
+    })
+}
+
+/// Lists the addresses of all interfaces using `getifaddrs(3)`.
+pub fn get_interface_addresses() -> Vec<InterfaceAddress> {
+    let mut addresses: Vec<InterfaceAddress> = Vec::new();
+
+    let mut ifaddrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: on success the list is owned by us until freeifaddrs
+    if unsafe { libc::getifaddrs(&mut ifaddrs) } != 0 {
+        return addresses;
+    }
 
-        // 2. Check if the interface is 'up'
-        let operstate = fs::read_to_string(path.join("operstate")).unwrap_or_default();
-        if !operstate.contains("up") {
+    let mut cursor = ifaddrs;
+    while !cursor.is_null() {
+        // SAFETY: cursor points into the list returned by getifaddrs
+        let ifaddr = unsafe { &*cursor };
+        cursor = ifaddr.ifa_next;
+        if ifaddr.ifa_addr.is_null() {
             continue;
         }
 
-        // 3. Check for carrier (physical connection detected)
-        let carrier = fs::read_to_string(path.join("carrier")).unwrap_or_default();
-        if carrier.trim() == "1" {
-            interfaces.push(iface);
-        }
+        // SAFETY: ifa_name is a NUL-terminated string and ifa_addr was checked
+        // above; the sockaddr is cast according to its address family
+        let (interface, address, broadcast) = unsafe {
+            let interface = CStr::from_ptr(ifaddr.ifa_name)
+                .to_string_lossy()
+                .into_owned();
+            match (*ifaddr.ifa_addr).sa_family as i32 {
+                libc::AF_INET => {
+                    let addr = &*(ifaddr.ifa_addr as *const libc::sockaddr_in);
+                    let broadcast = if ifaddr.ifa_flags & libc::IFF_BROADCAST as u32 != 0
+                        && !ifaddr.ifa_ifu.is_null()
+                    {
+                        let brd = &*(ifaddr.ifa_ifu as *const libc::sockaddr_in);
+                        Some(Ipv4Addr::from(u32::from_be(brd.sin_addr.s_addr)))
+                    } else {
+                        None
+                    };
+                    (
+                        interface,
+                        IpAddr::V4(Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr))),
+                        broadcast,
+                    )
+                }
+                libc::AF_INET6 => {
+                    let addr = &*(ifaddr.ifa_addr as *const libc::sockaddr_in6);
+                    (
+                        interface,
+                        IpAddr::V6(Ipv6Addr::from(addr.sin6_addr.s6_addr)),
+                        None,
+                    )
+                }
+                _ => continue,
+            }
+        };
+        addresses.push(InterfaceAddress {
+            interface,
+            address,
+            broadcast,
+        });
     }
 
-    interfaces
+    // SAFETY: ifaddrs was returned by getifaddrs and is not used afterwards
+    unsafe { libc::freeifaddrs(ifaddrs) };
+    addresses
+}
+
+/// Whether `network_interface` has addresses, all of them IPv4 link-local
+/// (APIPA) or IPv6 link-local ones, i.e. address configuration failed.
+pub fn has_only_link_local_addresses(network_interface: &str) -> bool {
+    let mut addresses = get_interface_addresses()
+        .into_iter()
+        .filter(|address| address.interface == network_interface)
+        .peekable();
+    addresses.peek().is_some()
+        && addresses.all(|address| match address.address {
+            IpAddr::V4(address) => address.is_link_local(),
+            IpAddr::V6(address) => address.is_unicast_link_local(),
+        })
 }
 
-pub fn get_received_bytes(network_interface: &str) -> Option<u64> {
-    let rx_bytes_path = format!("/sys/class/net/{}/statistics/rx_bytes", network_interface);
-    if let Ok(received_bytes_str) = fs::read_to_string(rx_bytes_path) {
-        return u64::from_str_radix(received_bytes_str.trim_end(), 10).ok();
+/// Parses a MAC address written as six hex octets separated by `:` or `-`.
+pub fn parse_mac_address(mac_address: &str) -> Option<[u8; 6]> {
+    let mut octets = [0u8; 6];
+    let mut parts = mac_address.trim().split([':', '-']);
+    for octet in octets.iter_mut() {
+        *octet = u8::from_str_radix(parts.next()?, 16).ok()?;
     }
-    None
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(octets)
 }
 
-pub fn get_sent_bytes(network_interface: &str) -> Option<u64> {
-    let tx_bytes_path = format!("/sys/class/net/{}/statistics/tx_bytes", network_interface);
-    if let Ok(sent_bytes_str) = fs::read_to_string(tx_bytes_path) {
-        return u64::from_str_radix(sent_bytes_str.trim_end(), 10).ok();
+/// Broadcasts a Wake-on-LAN magic packet for `mac_address` on the subnet of
+/// `network_interface`, falling back to the limited broadcast address.
+pub fn send_magic_packet(mac_address: [u8; 6], network_interface: Option<&str>) -> io::Result<()> {
+    let mut packet = vec![0xFF; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&mac_address);
+    }
+
+    let broadcast = network_interface
+        .and_then(|interface| {
+            get_interface_addresses()
+                .into_iter()
+                .find(|address| address.interface == interface && address.broadcast.is_some())
+        })
+        .and_then(|address| address.broadcast)
+        .unwrap_or(Ipv4Addr::BROADCAST);
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&packet, (broadcast, 9))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_stars_anywhere() {
+        assert!(glob_match("veth*", "veth0a1b"));
+        assert!(glob_match("*0", "eth0"));
+        assert!(glob_match("br-*-lan", "br-guest-lan"));
+        assert!(!glob_match("br-*-lan", "br-guest-wan"));
+        assert!(glob_match("*", "wlan0"));
+    }
+
+    #[test]
+    fn matches_one_character_per_question_mark() {
+        assert!(glob_match("docker?", "docker0"));
+        assert!(!glob_match("docker?", "docker10"));
+        assert!(glob_match("wl?n?", "wlan0"));
+    }
+
+    #[test]
+    fn matches_consecutive_stars_as_one() {
+        assert!(glob_match("v**h*", "veth0"));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("**x", "veth0"));
+    }
+
+    #[test]
+    fn matches_an_empty_pattern_only_to_an_empty_name() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "eth0"));
+        assert!(!is_excluded("eth0", &[String::new()]));
+    }
+
+    #[test]
+    fn matches_shorter_names_only_by_empty_stars() {
+        assert!(glob_match("veth*", "veth"));
+        assert!(!glob_match("docker?", "docker"));
+        assert!(!glob_match("eth0", "eth"));
     }
-    None
 }
@@ -0,0 +1,90 @@
+use {crate::host, std::io};
+
+/// A network device as seen by NetworkManager.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub name: String,
+    pub connected: bool,
+}
+
+/// Lists the devices NetworkManager manages, skipping loopback.
+pub fn get_devices() -> Vec<Device> {
+    let Ok(output) = host::command("nmcli")
+        .args(["-t", "-f", "DEVICE,TYPE,STATE", "device"])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let (name, device_type, state) = (fields.next()?, fields.next()?, fields.next()?);
+            if device_type == "loopback" || state == "unmanaged" {
+                return None;
+            }
+            Some(Device {
+                name: name.to_string(),
+                connected: state.starts_with("connected"),
+            })
+        })
+        .collect()
+}
+
+/// Connects or disconnects `device`. NetworkManager asks polkit for
+/// authorization when the user isn't allowed to do this directly.
+pub fn set_device_connected(device: &str, connected: bool) -> io::Result<()> {
+    let action = if connected { "connect" } else { "disconnect" };
+    let status = host::command("nmcli")
+        .args(["device", action, device])
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "nmcli device {action} {device} exited with {status}"
+        )))
+    }
+}
+
+/// Returns the name of the connection active on `device`, if any.
+pub fn get_active_connection(device: &str) -> Option<String> {
+    let output = host::command("nmcli")
+        .args(["-g", "GENERAL.CONNECTION", "device", "show", device])
+        .output()
+        .ok()?;
+    let connection = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!connection.is_empty()).then_some(connection)
+}
+
+/// Deactivates and reactivates the connection active on `device`.
+pub fn reconnect(device: &str) -> io::Result<()> {
+    let connection = get_active_connection(device)
+        .ok_or_else(|| io::Error::other(format!("no active connection on {device}")))?;
+    for action in ["down", "up"] {
+        let status = host::command("nmcli")
+            .args(["connection", action, "id", &connection])
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "nmcli connection {action} {connection} exited with {status}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the BSSID of the access point `device` is associated with.
+pub fn get_bssid(device: &str) -> Option<String> {
+    let output = host::command("nmcli")
+        .args(["-t", "-f", "ACTIVE,BSSID", "device", "wifi", "list"])
+        .args(["--rescan", "no", "ifname", device])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("yes:"))
+        // Terse output escapes the colons inside values
+        .map(|bssid| bssid.replace("\\:", ":"))
+}
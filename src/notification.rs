@@ -0,0 +1,16 @@
+use {crate::app::AppModel, cosmic::Application, notify_rust::Notification};
+
+/// Shows a desktop notification without blocking the caller.
+pub fn notify(summary: String, body: String) {
+    std::thread::spawn(move || {
+        if let Err(why) = Notification::new()
+            .appname(&crate::fl!("app-name"))
+            .icon(AppModel::APP_ID)
+            .summary(&summary)
+            .body(&body)
+            .show()
+        {
+            eprintln!("error while showing notification: {why}");
+        }
+    });
+}
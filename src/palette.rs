@@ -0,0 +1,68 @@
+use {crate::config::Palette, cosmic::iced::Color};
+
+/// Colors used for data and status indicators.
+pub struct Colors {
+    pub download: Color,
+    pub upload: Color,
+    pub good: Color,
+    pub warning: Color,
+    pub bad: Color,
+}
+
+fn rgb(hex: u32) -> Color {
+    Color::from_rgb8((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+/// Parses a `#rrggbb` color.
+pub fn parse_color(text: &str) -> Option<Color> {
+    let hex = text.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().map(rgb)
+}
+
+/// Picks a color for `network_interface` from the Okabe-Ito set, so the same
+/// name always gets the same color.
+pub fn interface_color(network_interface: &str) -> Color {
+    const COLORS: [u32; 7] = [
+        0xE69F00, 0x56B4E9, 0x009E73, 0xF0E442, 0x0072B2, 0xD55E00, 0xCC79A7,
+    ];
+    // FNV-1a, stable across runs unlike the std hasher
+    let hash = network_interface.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    });
+    rgb(COLORS[hash as usize % COLORS.len()])
+}
+
+impl Palette {
+    /// Resolves the palette, taking the theme's colors for [`Palette::Theme`].
+    /// The other palettes are picked from the Okabe-Ito color-blind safe set.
+    pub fn colors(&self, theme: &cosmic::cosmic_theme::Theme) -> Colors {
+        match self {
+            Palette::Theme => Colors {
+                download: theme.accent_color().into(),
+                upload: theme.success_color().into(),
+                good: theme.success_color().into(),
+                warning: theme.warning_color().into(),
+                bad: theme.destructive_color().into(),
+            },
+            // Safe for deuteranopia and protanopia: no red against green
+            Palette::RedGreenSafe => Colors {
+                download: rgb(0x56B4E9),
+                upload: rgb(0xE69F00),
+                good: rgb(0x0072B2),
+                warning: rgb(0xF0E442),
+                bad: rgb(0xD55E00),
+            },
+            // Safe for tritanopia: no blue against green or yellow
+            Palette::BlueYellowSafe => Colors {
+                download: rgb(0x009E73),
+                upload: rgb(0xCC79A7),
+                good: rgb(0x009E73),
+                warning: rgb(0xE69F00),
+                bad: rgb(0xD55E00),
+            },
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use {
+    crate::history::{History, Sample},
+    chrono::{DateTime, Local},
+    serde::{Deserialize, Serialize},
+    std::{env, fs, io, path::PathBuf},
+};
+
+/// A sample with its time as a Unix timestamp.
+#[derive(Deserialize, Serialize)]
+struct SavedSample {
+    time: i64,
+    received: u64,
+    sent: u64,
+    /// Number of samples averaged into this one
+    #[serde(default = "default_weight")]
+    weight: u64,
+}
+
+fn default_weight() -> u64 {
+    1
+}
+
+impl SavedSample {
+    fn new(sample: &Sample, weight: u64) -> Self {
+        SavedSample {
+            time: sample.time.timestamp(),
+            received: sample.received,
+            sent: sample.sent,
+            weight,
+        }
+    }
+
+    /// The sample and its weight, `None` if its time is out of range
+    fn restore(&self) -> Option<(Sample, u64)> {
+        let time = DateTime::from_timestamp(self.time, 0)?;
+        Some((
+            Sample {
+                time: time.with_timezone(&Local),
+                received: self.received,
+                sent: self.sent,
+            },
+            self.weight,
+        ))
+    }
+}
+
+/// What is kept across restarts of the applet.
+#[derive(Deserialize, Serialize)]
+struct SavedState {
+    /// Averages of the history, missing from states saved before it kept
+    /// them apart
+    #[serde(default)]
+    hours: Vec<SavedSample>,
+    #[serde(default)]
+    minutes: Vec<SavedSample>,
+    /// Samples kept as measured
+    samples: Vec<SavedSample>,
+}
+
+/// `$XDG_STATE_HOME/<app_id>/state.json`, defaulting to `~/.local/state`.
+fn state_path(app_id: &str) -> Option<PathBuf> {
+    let state_home = match env::var_os("XDG_STATE_HOME") {
+        Some(state_home) if !state_home.is_empty() => PathBuf::from(state_home),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state_home.join(app_id).join("state.json"))
+}
+
+/// Writes the history, replacing the previous state at once so an
+/// interrupted write leaves it intact.
+pub fn save(app_id: &str, history: &History) -> io::Result<()> {
+    let path = state_path(app_id).ok_or_else(|| io::Error::other("no state directory"))?;
+    let averages = |averages: &mut dyn Iterator<Item = (Sample, u64)>| -> Vec<SavedSample> {
+        averages
+            .map(|(sample, weight)| SavedSample::new(&sample, weight))
+            .collect()
+    };
+    let state = SavedState {
+        hours: averages(&mut history.hour_averages()),
+        minutes: averages(&mut history.minute_averages()),
+        samples: history
+            .measured()
+            .map(|sample| SavedSample::new(sample, 1))
+            .collect(),
+    };
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let partial_path = path.with_extension("json.partial");
+    fs::write(&partial_path, serde_json::to_vec(&state)?)?;
+    fs::rename(partial_path, path)
+}
+
+/// Restores the state saved by [`save`], dropping the samples the history
+/// retention no longer covers.
+pub fn load(app_id: &str, history: &mut History) {
+    let Some(contents) = state_path(app_id).and_then(|path| fs::read(path).ok()) else {
+        return;
+    };
+    let state: SavedState = match serde_json::from_slice(&contents) {
+        Ok(state) => state,
+        Err(why) => {
+            eprintln!("error while reading saved state: {why}");
+            return;
+        }
+    };
+    history.restore(
+        state.hours.iter().filter_map(SavedSample::restore),
+        state.minutes.iter().filter_map(SavedSample::restore),
+        state
+            .samples
+            .iter()
+            .filter_map(SavedSample::restore)
+            .map(|(sample, _)| sample),
+    );
+}
@@ -0,0 +1,114 @@
+use {
+    crate::{config::PluginConfig, host},
+    std::{
+        io::Read,
+        os::unix::process::CommandExt,
+        process::Stdio,
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+/// Time a command gets to print its readings before it is killed
+const POLL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time between two checks whether a command exited
+const POLL_STEP: Duration = Duration::from_millis(50);
+
+/// A labelled value reported by a data source.
+#[derive(Debug, Clone)]
+pub struct Reading {
+    pub label: String,
+    pub value: String,
+}
+
+/// A source of extra data that is rendered as its own card in the popup.
+pub trait DataSource: Send + Sync {
+    fn name(&self) -> &str;
+    /// Called from a blocking worker thread, never from the UI thread.
+    fn poll(&self) -> Vec<Reading>;
+}
+
+/// Runs a shell command and reads one `label=value` pair per line of its stdout.
+pub struct ExternalSource {
+    config: PluginConfig,
+}
+
+impl DataSource for ExternalSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn poll(&self) -> Vec<Reading> {
+        let mut command = host::command("sh");
+        command
+            .arg("-c")
+            .arg(&self.config.command)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            // A group of its own, so that its children are killed with it
+            .process_group(0);
+        let Ok(mut child) = command.spawn() else {
+            return Vec::new();
+        };
+        let Some(mut stdout) = child.stdout.take() else {
+            return Vec::new();
+        };
+        // Read while waiting, as a full pipe would stall the command
+        let reader = thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = stdout.read_to_end(&mut output);
+            output
+        });
+
+        let deadline = Instant::now() + POLL_TIMEOUT;
+        let timed_out = loop {
+            match child.try_wait() {
+                Ok(Some(_)) => break false,
+                Ok(None) if Instant::now() < deadline => thread::sleep(POLL_STEP),
+                _ => break true,
+            }
+        };
+        if timed_out {
+            eprintln!(
+                "error while polling plugin {}: no exit within {} s",
+                self.config.name,
+                POLL_TIMEOUT.as_secs()
+            );
+            // SAFETY: the group was created for the command, which was not
+            // reaped yet, so its ID was not reused
+            unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+            let _ = child.wait();
+        }
+        let output = reader.join().unwrap_or_default();
+
+        String::from_utf8_lossy(&output)
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(label, value)| Reading {
+                label: label.trim().to_string(),
+                value: value.trim().to_string(),
+            })
+            .collect()
+    }
+}
+
+pub fn get_data_sources(plugins: &[PluginConfig]) -> Vec<Arc<dyn DataSource>> {
+    plugins
+        .iter()
+        .filter(|plugin| !plugin.command.is_empty())
+        .map(|plugin| {
+            Arc::new(ExternalSource {
+                config: plugin.clone(),
+            }) as Arc<dyn DataSource>
+        })
+        .collect()
+}
+
+pub fn poll_data_sources(sources: &[Arc<dyn DataSource>]) -> Vec<(String, Vec<Reading>)> {
+    sources
+        .iter()
+        .map(|source| (source.name().to_string(), source.poll()))
+        .collect()
+}
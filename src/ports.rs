@@ -0,0 +1,88 @@
+use std::{collections::HashMap, fs};
+
+/// TCP socket state for a listening socket
+const TCP_LISTEN: &str = "0A";
+/// UDP socket state for an unconnected (bound) socket
+const UDP_UNCONNECTED: &str = "07";
+
+/// A locally listening socket.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ListeningPort {
+    pub protocol: &'static str,
+    pub port: u16,
+    /// Name of the owning process, unknown for other users' processes
+    pub process: Option<String>,
+}
+
+/// Maps socket inodes to the names of the processes holding them open.
+fn get_socket_owners() -> HashMap<u64, String> {
+    let mut owners: HashMap<u64, String> = HashMap::new();
+
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return owners;
+    };
+    for process in processes.flatten() {
+        let path = process.path();
+        // Permission is denied for other users' processes
+        let Ok(fds) = fs::read_dir(path.join("fd")) else {
+            continue;
+        };
+        let name = fs::read_to_string(path.join("comm")).unwrap_or_default();
+        for fd in fds.flatten() {
+            let Ok(target) = fs::read_link(fd.path()) else {
+                continue;
+            };
+            if let Some(inode) = target
+                .to_str()
+                .and_then(|target| target.strip_prefix("socket:["))
+                .and_then(|target| target.strip_suffix(']'))
+                .and_then(|inode| inode.parse().ok())
+            {
+                owners.insert(inode, name.trim().to_string());
+            }
+        }
+    }
+
+    owners
+}
+
+pub fn get_listening_ports() -> Vec<ListeningPort> {
+    let owners = get_socket_owners();
+    let mut ports: Vec<ListeningPort> = Vec::new();
+
+    for (protocol, table, listen_state) in [
+        ("TCP", "/proc/net/tcp", TCP_LISTEN),
+        ("TCP", "/proc/net/tcp6", TCP_LISTEN),
+        ("UDP", "/proc/net/udp", UDP_UNCONNECTED),
+        ("UDP", "/proc/net/udp6", UDP_UNCONNECTED),
+    ] {
+        let sockets = fs::read_to_string(table).unwrap_or_default();
+        // Skip the header line
+        for socket in sockets.lines().skip(1) {
+            let fields: Vec<&str> = socket.split_whitespace().collect();
+            if fields.len() < 10 || fields[3] != listen_state {
+                continue;
+            }
+            let Some(port) = fields[1]
+                .rsplit_once(':')
+                .and_then(|(_, port)| u16::from_str_radix(port, 16).ok())
+            else {
+                continue;
+            };
+            let process = fields[9]
+                .parse()
+                .ok()
+                .and_then(|inode: u64| owners.get(&inode).cloned());
+            ports.push(ListeningPort {
+                protocol,
+                port,
+                process,
+            });
+        }
+    }
+
+    // IPv4 and IPv6 sockets of the same service are listed once
+    ports.sort();
+    ports.dedup();
+    ports
+}
@@ -0,0 +1,42 @@
+use {
+    std::time::Duration,
+    tokio::{net::TcpStream, time::timeout},
+};
+
+/// How long a probe may take before the target counts as unreachable
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Turns a `host`, `host:port` or `http(s)://host[:port]/path` target into a
+/// `host:port` address, defaulting to the HTTPS port.
+pub fn get_probe_address(target: &str) -> String {
+    let (default_port, target) = if let Some(target) = target.strip_prefix("http://") {
+        (80, target)
+    } else {
+        (443, target.strip_prefix("https://").unwrap_or(target))
+    };
+    let host = target.split('/').next().unwrap_or_default();
+
+    let has_port = host.rsplit_once(':').is_some_and(|(address, port)| {
+        port.parse::<u16>().is_ok() && (!address.contains(':') || address.ends_with(']'))
+    });
+    if has_port {
+        host.to_string()
+    } else if host.contains(':') && !host.starts_with('[') {
+        // Bare IPv6 address
+        format!("[{host}]:{default_port}")
+    } else {
+        format!("{host}:{default_port}")
+    }
+}
+
+/// Checks whether a TCP connection to `target` can be established.
+pub async fn probe(target: String) -> bool {
+    matches!(
+        timeout(
+            PROBE_TIMEOUT,
+            TcpStream::connect(get_probe_address(&target))
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
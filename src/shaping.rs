@@ -0,0 +1,80 @@
+use {crate::host, std::io};
+
+/// A rate limit configured with tc on an interface.
+#[derive(Debug, Clone)]
+pub struct ShapingLimit {
+    pub qdisc: String,
+    /// Rate as printed by tc, e.g. `10Mbit`
+    pub rate: String,
+}
+
+fn tc_show(object: &str, network_interface: &str) -> String {
+    host::command("tc")
+        .args([object, "show", "dev", network_interface])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default()
+}
+
+/// Returns the value following `key` in a line of tc output.
+fn get_parameter<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let mut words = line.split_whitespace();
+    words.find(|word| *word == key)?;
+    words.next()
+}
+
+/// Reads the egress rate limit of the root qdisc of `network_interface`,
+/// supporting tbf and cake qdiscs as well as htb root classes.
+pub fn get_shaping_limit(network_interface: &str) -> Option<ShapingLimit> {
+    for line in tc_show("qdisc", network_interface).lines() {
+        let mut words = line.split_whitespace();
+        let (Some("qdisc"), Some(qdisc)) = (words.next(), words.next()) else {
+            continue;
+        };
+        let rate = match qdisc {
+            "tbf" => get_parameter(line, "rate").map(str::to_string),
+            "cake" => get_parameter(line, "bandwidth")
+                .filter(|rate| *rate != "unlimited")
+                .map(str::to_string),
+            // htb keeps its rates in classes rather than in the qdisc
+            "htb" => tc_show("class", network_interface)
+                .lines()
+                .find(|class| class.starts_with("class htb") && class.contains(" root "))
+                .and_then(|class| get_parameter(class, "rate"))
+                .map(str::to_string),
+            _ => None,
+        };
+        if let Some(rate) = rate {
+            return Some(ShapingLimit {
+                qdisc: qdisc.to_string(),
+                rate,
+            });
+        }
+    }
+    None
+}
+
+/// Applies a tbf egress limit of `rate_mbit` to `network_interface`, or
+/// removes the root qdisc when `rate_mbit` is `None`. pkexec asks polkit for
+/// authorization.
+pub fn set_shaping_limit(network_interface: &str, rate_mbit: Option<u32>) -> io::Result<()> {
+    let mut tc = host::command("pkexec");
+    tc.args(["tc", "qdisc"]);
+    match rate_mbit {
+        Some(rate) => {
+            let rate = format!("{rate}mbit");
+            tc.args(["replace", "dev", network_interface, "root", "tbf", "rate"])
+                .args([rate.as_str(), "burst", "32kbit", "latency", "400ms"]);
+        }
+        None => {
+            tc.args(["del", "dev", network_interface, "root"]);
+        }
+    }
+
+    let status = tc.status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("tc exited with {status}")))
+    }
+}
@@ -0,0 +1,12 @@
+use crate::host;
+
+/// Speaks `text` through speech-dispatcher, which screen readers such as
+/// Orca also speak through, without blocking the caller.
+pub fn announce(text: String) {
+    match host::command("spd-say").arg(text).spawn() {
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(why) => eprintln!("error while announcing rates: {why}"),
+    }
+}
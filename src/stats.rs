@@ -0,0 +1,439 @@
+use {
+    crate::{
+        config::StatsBackend,
+        netlink::{Link, LinkStatistics},
+        network,
+    },
+    std::{collections::BTreeMap, fs, io, path::Path, time::Duration},
+};
+
+/// Byte and packet counters of an interface, since it was created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counters {
+    pub received_bytes: u64,
+    pub sent_bytes: u64,
+    pub received_packets: u64,
+    pub sent_packets: u64,
+}
+
+/// Value the `unsigned long` counters of sysfs and `/proc/net/dev` wrap
+/// around at, as they are 32-bit on 32-bit kernels
+#[cfg(target_pointer_width = "32")]
+const UNSIGNED_LONG_LIMIT: Option<u64> = Some(1 << 32);
+#[cfg(not(target_pointer_width = "32"))]
+const UNSIGNED_LONG_LIMIT: Option<u64> = None;
+
+/// Where the active interfaces and their counters are read from.
+pub trait StatsSource: Send {
+    /// Called once per update, before the counters of that update are read,
+    /// and when links change, before the interfaces are listed
+    fn refresh(&mut self) {}
+    /// Interfaces that are up with a carrier, loopback aside, sorted by name,
+    /// as of the latest refresh for sources that read them all at once
+    fn interfaces(&self) -> Vec<String>;
+    /// Counters of `network_interface`, `None` if it does not exist
+    fn counters(&self, network_interface: &str) -> Option<Counters>;
+    /// Value the counters wrap around to 0 at, `None` for 64-bit counters,
+    /// which never do in practice
+    fn counter_limit(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Reads `/sys/class/net`.
+pub struct Sysfs;
+
+impl Sysfs {
+    /// Reads `statistics/<name>` of `network_interface`
+    fn read_statistic(network_interface: &str, name: &str) -> Option<u64> {
+        fs::read_to_string(
+            Path::new("/sys/class/net")
+                .join(network_interface)
+                .join("statistics")
+                .join(name),
+        )
+        .ok()?
+        .trim_end()
+        .parse()
+        .ok()
+    }
+}
+
+impl StatsSource for Sysfs {
+    fn interfaces(&self) -> Vec<String> {
+        let mut interfaces: Vec<String> = Vec::new();
+
+        let Ok(paths) = fs::read_dir("/sys/class/net") else {
+            return interfaces;
+        };
+        for entry in paths.flatten() {
+            let Ok(iface) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            // 1. Skip loopback
+            if iface == "lo" {
+                continue;
+            }
+
+            let path = entry.path();
+
+            // 2. Check if the interface is 'up'
+            let operstate = fs::read_to_string(path.join("operstate")).unwrap_or_default();
+            if !operstate.contains("up") {
+                continue;
+            }
+
+            // 3. Check for carrier (physical connection detected)
+            let carrier = fs::read_to_string(path.join("carrier")).unwrap_or_default();
+            if carrier.trim() == "1" {
+                interfaces.push(iface);
+            }
+        }
+
+        // Directory order is arbitrary, sort for a deterministic selection
+        interfaces.sort();
+        interfaces
+    }
+
+    fn counters(&self, network_interface: &str) -> Option<Counters> {
+        Some(Counters {
+            received_bytes: Self::read_statistic(network_interface, "rx_bytes")?,
+            sent_bytes: Self::read_statistic(network_interface, "tx_bytes")?,
+            received_packets: Self::read_statistic(network_interface, "rx_packets")?,
+            sent_packets: Self::read_statistic(network_interface, "tx_packets")?,
+        })
+    }
+
+    fn counter_limit(&self) -> Option<u64> {
+        UNSIGNED_LONG_LIMIT
+    }
+}
+
+/// Parses `/proc/net/dev`, which has the counters of every interface, once
+/// per refresh.
+#[derive(Default)]
+pub struct ProcNetDev {
+    /// Counters read by the latest refresh, by interface
+    counters: BTreeMap<String, Counters>,
+}
+
+impl ProcNetDev {
+    /// Reads the counters of each line, `name: <8 receive fields> <8
+    /// transmit fields>` with bytes then packets first in each half
+    fn parse(contents: &str) -> BTreeMap<String, Counters> {
+        contents
+            .lines()
+            // The two header lines have no colon
+            .filter_map(|line| line.split_once(':'))
+            .filter_map(|(name, fields)| {
+                let fields: Vec<u64> = fields
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .ok()?;
+                Some((
+                    name.trim().to_string(),
+                    Counters {
+                        received_bytes: *fields.first()?,
+                        received_packets: *fields.get(1)?,
+                        sent_bytes: *fields.get(8)?,
+                        sent_packets: *fields.get(9)?,
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+impl StatsSource for ProcNetDev {
+    fn refresh(&mut self) {
+        match fs::read_to_string("/proc/net/dev") {
+            Ok(contents) => self.counters = Self::parse(&contents),
+            Err(why) => eprintln!("error while reading /proc/net/dev: {why}"),
+        }
+    }
+
+    /// `/proc/net/dev` has no link state, which is read from sysfs
+    fn interfaces(&self) -> Vec<String> {
+        Sysfs.interfaces()
+    }
+
+    fn counters(&self, network_interface: &str) -> Option<Counters> {
+        self.counters.get(network_interface).copied()
+    }
+
+    fn counter_limit(&self) -> Option<u64> {
+        UNSIGNED_LONG_LIMIT
+    }
+}
+
+/// Reads every interface with a single netlink request per refresh.
+pub struct Netlink {
+    link_statistics: LinkStatistics,
+    /// Links read by the latest refresh
+    links: Vec<Link>,
+}
+
+impl Netlink {
+    /// Opens the netlink socket, failing if the first request does
+    pub fn new() -> io::Result<Self> {
+        let link_statistics = LinkStatistics::new()?;
+        let links = link_statistics.dump()?;
+        Ok(Netlink {
+            link_statistics,
+            links,
+        })
+    }
+}
+
+impl StatsSource for Netlink {
+    fn refresh(&mut self) {
+        match self.link_statistics.dump() {
+            Ok(links) => self.links = links,
+            Err(why) => eprintln!("error while reading interface statistics: {why}"),
+        }
+    }
+
+    fn interfaces(&self) -> Vec<String> {
+        let mut interfaces: Vec<String> = self
+            .links
+            .iter()
+            .filter(|link| link.active && link.name != "lo")
+            .map(|link| link.name.clone())
+            .collect();
+        interfaces.sort();
+        interfaces
+    }
+
+    fn counters(&self, network_interface: &str) -> Option<Counters> {
+        self.links
+            .iter()
+            .find(|link| link.name == network_interface)
+            .map(|link| link.counters)
+    }
+}
+
+/// Traffic counted between two readings of a counter. A counter that went
+/// back wrapped around if it is known to at `limit`, otherwise it was reset,
+/// in which case nothing is counted and the new reading becomes the
+/// baseline.
+pub fn counter_delta(current: u64, previous: u64, limit: Option<u64>) -> u64 {
+    match (current.checked_sub(previous), limit) {
+        (Some(delta), _) => delta,
+        (None, Some(limit)) if previous < limit => limit - previous + current,
+        (None, _) => 0,
+    }
+}
+
+/// Rate of `count` counted over `elapsed`, per second.
+pub fn per_second(count: u64, elapsed: Duration) -> u64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        (count as f64 / seconds) as u64
+    } else {
+        0
+    }
+}
+
+/// Sums the counters of `network_interfaces`, counting bonds and bridges but
+/// not their members.
+pub fn total_counters(source: &dyn StatsSource, network_interfaces: &[String]) -> Counters {
+    network::without_enslaved(network_interfaces)
+        .filter_map(|network_interface| source.counters(network_interface))
+        .fold(Counters::default(), |total, counters| Counters {
+            received_bytes: total.received_bytes + counters.received_bytes,
+            sent_bytes: total.sent_bytes + counters.sent_bytes,
+            received_packets: total.received_packets + counters.received_packets,
+            sent_packets: total.sent_packets + counters.sent_packets,
+        })
+}
+
+/// The source read by `backend`, falling back to sysfs where netlink is not
+/// available.
+pub fn get_stats_source(backend: StatsBackend) -> Box<dyn StatsSource> {
+    match backend {
+        StatsBackend::Netlink => match Netlink::new() {
+            Ok(netlink) => Box::new(netlink),
+            Err(why) => {
+                eprintln!("error while reading interface statistics over netlink: {why}");
+                Box::new(Sysfs)
+            }
+        },
+        StatsBackend::Sysfs => Box::new(Sysfs),
+        StatsBackend::ProcNetDev => {
+            let mut proc_net_dev = ProcNetDev::default();
+            proc_net_dev.refresh();
+            Box::new(proc_net_dev)
+        }
+    }
+}
+
+/// Made-up interfaces whose counters grow by the same amounts on every
+/// refresh, so the same updates always measure the same traffic.
+#[cfg(test)]
+pub struct Mock {
+    /// Counters of each interface, with what every refresh adds to them
+    interfaces: BTreeMap<String, (Counters, Counters)>,
+    limit: Option<u64>,
+}
+
+#[cfg(test)]
+impl Mock {
+    /// Interfaces starting from zero, with what every refresh adds to them
+    pub fn new<'a>(interfaces: impl IntoIterator<Item = (&'a str, Counters)>) -> Self {
+        Mock {
+            interfaces: interfaces
+                .into_iter()
+                .map(|(name, step)| (name.to_string(), (Counters::default(), step)))
+                .collect(),
+            limit: None,
+        }
+    }
+
+    /// Makes the counters wrap around at `limit`, as 32-bit ones do
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Replaces the counters of `network_interface`, as a driver resetting
+    /// them would
+    pub fn set(&mut self, network_interface: &str, counters: Counters) {
+        if let Some(interface) = self.interfaces.get_mut(network_interface) {
+            interface.0 = counters;
+        }
+    }
+}
+
+#[cfg(test)]
+impl StatsSource for Mock {
+    fn refresh(&mut self) {
+        let add = |counter: &mut u64, step: u64| {
+            *counter = match self.limit {
+                Some(limit) => (*counter + step) % limit,
+                None => *counter + step,
+            };
+        };
+        for (counters, step) in self.interfaces.values_mut() {
+            add(&mut counters.received_bytes, step.received_bytes);
+            add(&mut counters.sent_bytes, step.sent_bytes);
+            add(&mut counters.received_packets, step.received_packets);
+            add(&mut counters.sent_packets, step.sent_packets);
+        }
+    }
+
+    fn interfaces(&self) -> Vec<String> {
+        self.interfaces.keys().cloned().collect()
+    }
+
+    fn counters(&self, network_interface: &str) -> Option<Counters> {
+        self.interfaces
+            .get(network_interface)
+            .map(|(counters, _)| *counters)
+    }
+
+    fn counter_limit(&self) -> Option<u64> {
+        self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 155704937   14606    0    0    0     0          0         0 155704937   14606    0    0    0     0       0          0
+  eth0:    1412      21    0    0    0     0          0         0     1713      17    0    0    0     0       0          0
+";
+
+    #[test]
+    fn parses_proc_net_dev() {
+        let counters = ProcNetDev::parse(PROC_NET_DEV);
+        assert_eq!(counters.len(), 2);
+        assert_eq!(
+            counters["eth0"],
+            Counters {
+                received_bytes: 1412,
+                sent_bytes: 1713,
+                received_packets: 21,
+                sent_packets: 17,
+            }
+        );
+        assert_eq!(counters["lo"].sent_bytes, 155704937);
+    }
+
+    #[test]
+    fn skips_malformed_proc_net_dev_lines() {
+        let counters = ProcNetDev::parse("eth0: 1 2 3\nwlan0: a b c d e f g h i j\n");
+        assert!(counters.is_empty());
+    }
+
+    #[test]
+    fn counts_increasing_counters() {
+        assert_eq!(counter_delta(1500, 1000, None), 500);
+        assert_eq!(counter_delta(1000, 1000, None), 0);
+        assert_eq!(counter_delta(1500, 1000, Some(1 << 32)), 500);
+    }
+
+    #[test]
+    fn rebaselines_reset_counters() {
+        assert_eq!(counter_delta(100, 3_200_000_000_000, None), 0);
+        // Within 32 bits, yet a reset of a 64-bit counter
+        assert_eq!(counter_delta(100, 3_200_000_000, None), 0);
+    }
+
+    #[test]
+    fn counts_wrapped_32_bit_counters() {
+        assert_eq!(counter_delta(100, u32::MAX as u64 - 99, Some(1 << 32)), 200);
+    }
+
+    #[test]
+    fn wraps_unsigned_long_counters_on_32_bit_kernels() {
+        let previous = u32::MAX as u64 - 99;
+        for limit in [Sysfs.counter_limit(), ProcNetDev::default().counter_limit()] {
+            let delta = counter_delta(100, previous, limit);
+            if cfg!(target_pointer_width = "32") {
+                assert_eq!(delta, 200);
+            } else {
+                assert_eq!(delta, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn mock_counters_grow_on_refresh_only() {
+        let step = Counters {
+            received_bytes: 1000,
+            sent_bytes: 200,
+            received_packets: 1,
+            sent_packets: 1,
+        };
+        let mut mock = Mock::new([("mock0", step)]);
+        assert_eq!(mock.counters("mock0"), Some(Counters::default()));
+        mock.refresh();
+        mock.refresh();
+        assert_eq!(mock.counters("mock0").unwrap().received_bytes, 2000);
+        assert_eq!(mock.counters("mock1"), None);
+    }
+
+    #[test]
+    fn totals_every_interface() {
+        let step = |received_bytes| Counters {
+            received_bytes,
+            ..Counters::default()
+        };
+        let mut mock = Mock::new([("mock0", step(1000)), ("mock1", step(250))]);
+        mock.refresh();
+        let total = total_counters(&mock, &mock.interfaces());
+        assert_eq!(total.received_bytes, 1250);
+    }
+
+    #[test]
+    fn rates_over_the_elapsed_time() {
+        assert_eq!(per_second(1000, Duration::from_millis(500)), 2000);
+        assert_eq!(per_second(1000, Duration::ZERO), 0);
+    }
+}
@@ -0,0 +1,90 @@
+use {
+    opentelemetry::{
+        KeyValue,
+        metrics::{Counter, Gauge, MeterProvider},
+    },
+    opentelemetry_otlp::MetricExporter,
+    opentelemetry_sdk::{Resource, metrics::SdkMeterProvider},
+    std::time::Duration,
+};
+
+/// Direction of traffic, exported as the `network.io.direction` attribute
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    Receive,
+    Transmit,
+}
+
+/// Exports rates and totals over OTLP/HTTP. The endpoint, headers and export
+/// interval are taken from the standard `OTEL_EXPORTER_OTLP_*` and
+/// `OTEL_METRIC_EXPORT_INTERVAL` environment variables.
+pub struct Telemetry {
+    provider: SdkMeterProvider,
+    rate: Gauge<u64>,
+    total: Counter<u64>,
+}
+
+impl Telemetry {
+    pub fn new(service_name: &'static str) -> Option<Self> {
+        let exporter = match MetricExporter::builder().with_http().build() {
+            Ok(exporter) => exporter,
+            Err(why) => {
+                eprintln!("error while creating OTLP metrics exporter: {why}");
+                return None;
+            }
+        };
+        let provider = SdkMeterProvider::builder()
+            .with_resource(Resource::builder().with_service_name(service_name).build())
+            .with_periodic_exporter(exporter)
+            .build();
+
+        let meter = provider.meter(service_name);
+        let rate = meter
+            .u64_gauge("network.io.rate")
+            .with_description("Current transfer rate of the monitored interface")
+            .with_unit("By/s")
+            .build();
+        let total = meter
+            .u64_counter("network.io")
+            .with_description("Bytes transferred on the monitored interface")
+            .with_unit("By")
+            .build();
+
+        Some(Telemetry {
+            provider,
+            rate,
+            total,
+        })
+    }
+
+    /// Records `bytes` transferred in `direction` over `elapsed`.
+    pub fn record(
+        &self,
+        network_interface: &str,
+        direction: Direction,
+        bytes: u64,
+        elapsed: Duration,
+    ) {
+        let attributes = [
+            KeyValue::new("network.interface.name", network_interface.to_string()),
+            KeyValue::new(
+                "network.io.direction",
+                match direction {
+                    Direction::Receive => "receive",
+                    Direction::Transmit => "transmit",
+                },
+            ),
+        ];
+        self.total.add(bytes, &attributes);
+        self.rate
+            .record(crate::stats::per_second(bytes, elapsed), &attributes);
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(why) = self.provider.shutdown() {
+            eprintln!("error while shutting down OTLP metrics exporter: {why}");
+        }
+    }
+}
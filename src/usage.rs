@@ -0,0 +1,151 @@
+use {
+    chrono::{Datelike, Days, Local, Months, NaiveDate},
+    std::{collections::BTreeMap, ops::RangeBounds},
+};
+
+/// `reset_day` of the month of `year`, or its last day for shorter months.
+fn reset_date(year: i32, month: u32, reset_day: u32) -> Option<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+    first.with_day(reset_day.clamp(1, last.day()))
+}
+
+/// First day of the monthly cycle containing `day`, for cycles starting
+/// on `reset_day` of every month.
+pub fn cycle_start(day: NaiveDate, reset_day: u8) -> Option<NaiveDate> {
+    let reset_day = reset_day as u32;
+    let start = reset_date(day.year(), day.month(), reset_day)?;
+    if day >= start {
+        return Some(start);
+    }
+    let previous_month = day.with_day(1)?.checked_sub_months(Months::new(1))?;
+    reset_date(previous_month.year(), previous_month.month(), reset_day)
+}
+
+/// Bytes received and sent over a calendar day.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DailyUsage {
+    pub received_bytes: u64,
+    pub sent_bytes: u64,
+}
+
+/// Accounts traffic per local calendar day.
+#[derive(Debug, Default, Clone)]
+pub struct UsageAccounting {
+    days: BTreeMap<NaiveDate, DailyUsage>,
+}
+
+impl UsageAccounting {
+    /// Adds traffic to the current day
+    pub fn record(&mut self, received_bytes: u64, sent_bytes: u64) {
+        self.add(Local::now().date_naive(), received_bytes, sent_bytes);
+    }
+
+    /// Adds traffic to `day`, such as the usage saved before a restart
+    pub fn add(&mut self, day: NaiveDate, received_bytes: u64, sent_bytes: u64) {
+        let usage = self.days.entry(day).or_default();
+        usage.received_bytes += received_bytes;
+        usage.sent_bytes += sent_bytes;
+    }
+
+    /// Traffic of `day`, empty if nothing was recorded on it
+    pub fn get(&self, day: NaiveDate) -> DailyUsage {
+        self.days.get(&day).copied().unwrap_or_default()
+    }
+
+    pub fn today(&self) -> DailyUsage {
+        self.get(Local::now().date_naive())
+    }
+
+    pub fn yesterday(&self) -> DailyUsage {
+        Local::now()
+            .date_naive()
+            .pred_opt()
+            .map(|day| self.get(day))
+            .unwrap_or_default()
+    }
+
+    /// Traffic of the days within `range`
+    fn sum(&self, range: impl RangeBounds<NaiveDate>) -> DailyUsage {
+        self.days
+            .range(range)
+            .fold(DailyUsage::default(), |total, (_, usage)| DailyUsage {
+                received_bytes: total.received_bytes + usage.received_bytes,
+                sent_bytes: total.sent_bytes + usage.sent_bytes,
+            })
+    }
+
+    /// Traffic since `start`, included
+    pub fn since(&self, start: NaiveDate) -> DailyUsage {
+        self.sum(start..)
+    }
+
+    /// Traffic of the same weekday as today, a week ago
+    pub fn last_week(&self) -> DailyUsage {
+        Local::now()
+            .date_naive()
+            .checked_sub_days(Days::new(7))
+            .map(|day| self.get(day))
+            .unwrap_or_default()
+    }
+
+    /// Traffic of the current monthly cycle, starting on `reset_day`
+    pub fn this_cycle(&self, reset_day: u8) -> DailyUsage {
+        cycle_start(Local::now().date_naive(), reset_day)
+            .map(|start| self.since(start))
+            .unwrap_or_default()
+    }
+
+    /// Traffic of the monthly cycle before the current one, over as many
+    /// days as the current one has had so far
+    pub fn previous_cycle_to_date(&self, reset_day: u8) -> DailyUsage {
+        self.previous_cycle_until(Local::now().date_naive(), reset_day)
+    }
+
+    /// Traffic of the monthly cycle before the one of `day`, up to the same
+    /// day of that cycle, included
+    fn previous_cycle_until(&self, day: NaiveDate, reset_day: u8) -> DailyUsage {
+        cycle_start(day, reset_day)
+            .and_then(|start| {
+                let previous_start = cycle_start(start.pred_opt()?, reset_day)?;
+                let elapsed = Days::new((day - start).num_days() as u64 + 1);
+                let end = previous_start.checked_add_days(elapsed)?;
+                // A longer cycle does not count past the previous one
+                Some(self.sum(previous_start..end.min(start)))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Every day with traffic, from the oldest to the newest
+    pub fn days(&self) -> impl Iterator<Item = (NaiveDate, DailyUsage)> {
+        self.days.iter().map(|(day, usage)| (*day, *usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn compares_the_same_days_of_the_previous_cycle() {
+        let mut usage = UsageAccounting::default();
+        usage.add(date(2026, 2, 1), 100, 0);
+        usage.add(date(2026, 2, 5), 10, 0);
+        usage.add(date(2026, 2, 6), 1, 0);
+        let previous = usage.previous_cycle_until(date(2026, 3, 5), 1);
+        assert_eq!(previous.received_bytes, 110);
+    }
+
+    #[test]
+    fn compares_at_most_the_whole_previous_cycle() {
+        let mut usage = UsageAccounting::default();
+        usage.add(date(2026, 2, 28), 10, 0);
+        usage.add(date(2026, 3, 1), 1, 0);
+        let previous = usage.previous_cycle_until(date(2026, 3, 31), 1);
+        assert_eq!(previous.received_bytes, 10);
+    }
+}
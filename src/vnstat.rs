@@ -0,0 +1,99 @@
+use {
+    chrono::{Datelike, Months, NaiveDate},
+    rusqlite::{Connection, OpenFlags},
+    std::{collections::BTreeMap, path::Path},
+};
+
+/// Traffic vnStat recorded for an interface over a day or a month.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub interface: String,
+    pub start: NaiveDate,
+    /// First day after the record
+    pub end: NaiveDate,
+    pub received_bytes: u64,
+    pub sent_bytes: u64,
+}
+
+/// Reads the records of `table`, whose dates start with `YYYY-MM-DD`.
+fn read_table(
+    connection: &Connection,
+    table: &str,
+) -> rusqlite::Result<Vec<(String, NaiveDate, u64, u64)>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT interface.name, {table}.date, {table}.rx, {table}.tx
+            FROM {table} JOIN interface ON interface.id = {table}.interface"
+    ))?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+    let mut records = Vec::new();
+    for row in rows {
+        let (interface, date, received_bytes, sent_bytes) = row?;
+        // Dates may be followed by a time
+        match date.get(..10).unwrap_or(&date).parse::<NaiveDate>() {
+            Ok(day) => records.push((interface, day, received_bytes as u64, sent_bytes as u64)),
+            Err(why) => {
+                eprintln!("error while reading vnStat record of {interface} on {date}: {why}")
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Reads the daily and monthly totals of every interface of a vnStat 2
+/// database.
+pub fn read(path: &Path) -> rusqlite::Result<Vec<Record>> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut records: Vec<Record> = read_table(&connection, "day")?
+        .into_iter()
+        .filter_map(|(interface, start, received_bytes, sent_bytes)| {
+            Some(Record {
+                interface,
+                start,
+                end: start.succ_opt()?,
+                received_bytes,
+                sent_bytes,
+            })
+        })
+        .collect();
+
+    let mut days_by_month: BTreeMap<(String, NaiveDate), (u64, u64)> = BTreeMap::new();
+    for record in &records {
+        if let Some(month) = record.start.with_day(1) {
+            let days = days_by_month
+                .entry((record.interface.clone(), month))
+                .or_default();
+            days.0 += record.received_bytes;
+            days.1 += record.sent_bytes;
+        }
+    }
+    // Months older than the days vnStat keeps, or only partly covered by
+    // them, are imported as the rest of their total over the whole month
+    for (interface, start, received_bytes, sent_bytes) in read_table(&connection, "month")? {
+        let (days_received, days_sent) = days_by_month
+            .get(&(interface.clone(), start))
+            .copied()
+            .unwrap_or_default();
+        let received_bytes = received_bytes.saturating_sub(days_received);
+        let sent_bytes = sent_bytes.saturating_sub(days_sent);
+        if received_bytes == 0 && sent_bytes == 0 {
+            continue;
+        }
+        if let Some(end) = start.checked_add_months(Months::new(1)) {
+            records.push(Record {
+                interface,
+                start,
+                end,
+                received_bytes,
+                sent_bytes,
+            });
+        }
+    }
+    Ok(records)
+}